@@ -0,0 +1,80 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module contains the database entities of the router and the migration logic creating their tables. The database is used to
+//! persist the configuration of the router like the configured BGP peers or the users of the web interface.
+
+pub mod peer;
+pub mod router_config;
+pub mod user;
+
+#[cfg(test)]
+pub mod tests;
+
+use argon2::{Argon2, PasswordHasher};
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use log::warn;
+use rand::distr::{Alphanumeric, SampleString};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait, Schema, Set};
+use crate::protocols::bgp::BgpIdentifier;
+
+/// This function creates all tables of the router's entities, if they don't already exist in the database.
+pub async fn migrate(database: &DatabaseConnection) -> anyhow::Result<()> {
+    let backend = database.get_database_backend();
+    let schema = Schema::new(backend);
+    database.execute(backend.build(schema.create_table_from_entity(peer::Entity).if_not_exists())).await?;
+    database.execute(backend.build(schema.create_table_from_entity(user::Entity).if_not_exists())).await?;
+    database.execute(backend.build(schema.create_table_from_entity(router_config::Entity).if_not_exists())).await?;
+    Ok(())
+}
+
+/// This function creates the `admin` user with a randomly generated password if no user is present in the database. If the user was
+/// created, this function returns the generated password, so it can be shown to the operator once.
+pub async fn create_admin_user(database: &DatabaseConnection) -> anyhow::Result<Option<String>> {
+    if user::Entity::find().count(database).await? > 0 {
+        return Ok(None);
+    }
+
+    let password = Alphanumeric.sample_string(&mut rand::rng(), 24);
+    let password_hash = Argon2::default().hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map_err(|error| anyhow::anyhow!("Unable to hash password => {}", error))?
+        .to_string();
+    user::ActiveModel { username: Set("admin".to_string()), password_hash: Set(password_hash), ..Default::default() }
+        .insert(database)
+        .await?;
+    Ok(Some(password))
+}
+
+/// This function loads the configuration of the router. On the first start, the configuration is created with the private AS number 64512,
+/// the router ID derived from the local interfaces and the BGP port on all addresses. If the router ID can't be derived (like on operating
+/// systems other than Linux or hosts without IPv4 addresses), [BgpIdentifier::PLACEHOLDER] is used instead of aborting the start. The
+/// second value is true, if the configuration was created, so the operator can be asked to change it.
+pub async fn load_router_config(database: &DatabaseConnection) -> anyhow::Result<(router_config::Model, bool)> {
+    if let Some(config) = router_config::Entity::find().one(database).await? {
+        return Ok((config, false));
+    }
+
+    let router_id = BgpIdentifier::from_interface().unwrap_or_else(|error| {
+        warn!("Unable to derive router ID from the local interfaces => {}, using {} instead", error, BgpIdentifier::PLACEHOLDER);
+        BgpIdentifier::PLACEHOLDER
+    });
+    let config = router_config::ActiveModel {
+        local_asn: Set(64512),
+        router_id: Set(router_id.to_string()),
+        listen_addresses: Set("0.0.0.0:179,[::]:179".to_string()),
+        ..Default::default()
+    }.insert(database).await?;
+    Ok((config, true))
+}
@@ -0,0 +1,57 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::Ipv4Addr;
+use sea_orm::entity::prelude::*;
+use crate::protocols::bgp::OpenMessage;
+use crate::protocols::bgp::params::OptionalParameter;
+use crate::protocols::bgp::rfc3392::Capability;
+use crate::protocols::bgp::rfc6793::{FourOctetASNumberSupportCapability, AS_TRANS};
+
+/// The hold time proposed in the open message, if not configured otherwise
+pub const DEFAULT_HOLD_TIME: u16 = 90;
+
+/// This entity represents the configuration of the router itself, which is stored in a single row. The listen addresses are stored as
+/// comma-separated list of socket addresses.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "router_config")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub local_asn: i64,
+    pub router_id: String,
+    pub listen_addresses: String
+}
+
+impl Model {
+    /// This function creates the template of the open message sent to the peers from this configuration. The four-octet AS number support
+    /// capability is always advertised, so AS numbers not fitting into two bytes are sent as `AS_TRANS` in the open message.
+    pub fn open_message(&self, hold_time: u16) -> anyhow::Result<OpenMessage> {
+        let local_asn = u32::try_from(self.local_asn).map_err(|_| anyhow::anyhow!("Local AS number {} is out of range", self.local_asn))?;
+        let router_id: Ipv4Addr = self.router_id.parse()
+            .map_err(|error| anyhow::anyhow!("Router ID '{}' is no valid IPv4 address => {}", self.router_id, error))?;
+        let capabilities = vec![Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: local_asn })];
+        Ok(OpenMessage::new(
+            u16::try_from(local_asn).unwrap_or(AS_TRANS),
+            hold_time,
+            router_id,
+            vec![OptionalParameter::Capabilities(capabilities)]
+        )?)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
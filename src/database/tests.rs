@@ -0,0 +1,67 @@
+use std::net::Ipv4Addr;
+use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait, PaginatorTrait, Set};
+use crate::database::{create_admin_user, load_router_config, migrate, peer, router_config, user};
+use crate::protocols::bgp::params::OptionalParameter;
+use crate::protocols::bgp::rfc3392::Capability;
+use crate::protocols::bgp::rfc6793::{FourOctetASNumberSupportCapability, AS_TRANS};
+
+async fn connect_memory_database() -> DatabaseConnection {
+    let database = Database::connect("sqlite::memory:").await.unwrap();
+    migrate(&database).await.unwrap();
+    database
+}
+
+#[tokio::test]
+async fn insert_and_read_peer() {
+    let database = connect_memory_database().await;
+    let peer = peer::ActiveModel {
+        name: Set("Upstream".to_string()),
+        remote_address: Set("192.0.2.1".to_string()),
+        remote_asn: Set(4200000000),
+        local_asn: Set(65001),
+        hold_time: Set(90),
+        enabled: Set(true),
+        ..Default::default()
+    }.insert(&database).await.unwrap();
+
+    let peers = peer::Entity::find().all(&database).await.unwrap();
+    assert_eq!(vec![peer], peers);
+    assert_eq!(4200000000, peers[0].remote_asn);
+}
+
+#[tokio::test]
+async fn create_admin_user_once() {
+    let database = connect_memory_database().await;
+    assert!(create_admin_user(&database).await.unwrap().is_some());
+    assert!(create_admin_user(&database).await.unwrap().is_none());
+    assert_eq!(1, user::Entity::find().count(&database).await.unwrap());
+}
+
+#[tokio::test]
+async fn load_router_config_created_once() {
+    // The router ID falls back to the placeholder on hosts without a derivable router ID, so the first start never fails
+    let database = connect_memory_database().await;
+    let (config, created) = load_router_config(&database).await.unwrap();
+    assert!(created);
+    assert!(config.open_message(90).is_ok());
+    assert_eq!((config, false), load_router_config(&database).await.unwrap());
+}
+
+#[tokio::test]
+async fn load_router_config_open_message() {
+    let database = connect_memory_database().await;
+    router_config::ActiveModel {
+        local_asn: Set(4200000000),
+        router_id: Set("192.0.2.1".to_string()),
+        listen_addresses: Set("0.0.0.0:179".to_string()),
+        ..Default::default()
+    }.insert(&database).await.unwrap();
+
+    let (config, created) = load_router_config(&database).await.unwrap();
+    assert!(!created);
+    let open_message = config.open_message(90).unwrap();
+    assert_eq!(AS_TRANS, open_message.autonomous_system);
+    assert_eq!(Ipv4Addr::new(192, 0, 2, 1), open_message.bgp_identifier_ip());
+    let capability = Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 4200000000 });
+    assert_eq!(vec![OptionalParameter::Capabilities(vec![capability])], open_message.optional_parameters);
+}
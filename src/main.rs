@@ -14,31 +14,116 @@
 
 pub mod protocols;
 pub mod prefix;
+pub mod database;
+pub mod web;
 
 use std::fs;
+use std::net::Ipv4Addr;
 use std::process::exit;
+use clap::{Parser, Subcommand};
 use colorful::{Color, Colorful};
-use log::{error, LevelFilter};
-use sea_orm::{ConnectOptions, DatabaseConnection};
+use log::{error, info, warn, LevelFilter};
+use sea_orm::{ColumnTrait, ConnectOptions, DatabaseConnection, EntityTrait, QueryFilter};
 use simple_logger::SimpleLogger;
+use crate::protocols::bgp::BGPMessage;
+use crate::database::router_config::DEFAULT_HOLD_TIME;
+use crate::protocols::bgp::params::OptionalParameter;
+
+#[derive(Parser)]
+#[command(version, about = "Modern BGP Router by Cach30verfl0w")]
+struct Arguments {
+    #[command(subcommand)]
+    command: Option<Command>
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decodes the BGP messages in the specified binary file or hex string and prints them
+    Decode {
+        /// Path to a file containing the raw messages or the messages as hex string
+        input: String
+    }
+}
 
 async fn connect_database(database_url: &str) -> anyhow::Result<DatabaseConnection> {
     if database_url.starts_with("sqlite://") {
         let path = database_url.replace("sqlite://", "");
         if !fs::exists(&path).unwrap_or(false) {
-            fs::write(path, &[])?;
+            fs::write(path, [])?;
         }
     }
     Ok(sea_orm::Database::connect(ConnectOptions::from(database_url)).await?)
 }
 
+fn print_message(index: usize, message: &BGPMessage) {
+    match message {
+        BGPMessage::Open(open) => {
+            println!("#{} OPEN (Version {}, AS{}, Hold time {}s, Identifier {})", index, open.version, open.autonomous_system,
+                     open.hold_time, Ipv4Addr::from(open.bgp_identifier));
+            for parameter in &open.optional_parameters {
+                match parameter {
+                    OptionalParameter::Capabilities(capabilities) => capabilities.iter()
+                        .for_each(|capability| println!("  Capability: {}", capability)),
+                    OptionalParameter::Authentication { auth_code, data } => {
+                        println!("  Authentication parameter {} bytes (Code: {})", data.len(), auth_code)
+                    },
+                    OptionalParameter::Unknown { kind, data } => println!("  Unknown parameter {} bytes (Kind: {})", data.len(), kind)
+                }
+            }
+        },
+        BGPMessage::Update(update) => {
+            println!("#{} UPDATE ({} withdrawn, {} path attributes, {} NLRI)", index, update.withdrawn_routes.len(),
+                     update.path_attributes.len(), update.network_layer_reachability_information.len());
+            update.withdrawn_routes.iter().for_each(|prefix| println!("  Withdrawn: {}", prefix));
+            update.path_attributes.iter().for_each(|attribute| println!("  Path attribute: {}", attribute));
+            update.network_layer_reachability_information.iter().for_each(|prefix| println!("  NLRI: {}", prefix));
+        },
+        BGPMessage::KeepAlive => println!("#{} KEEPALIVE", index),
+        BGPMessage::Notification(notification) => println!("#{} NOTIFICATION (Error code {}, subcode {}, {} bytes data)", index,
+                                                           notification.error_code, notification.error_subcode, notification.data.len()),
+        BGPMessage::Unknown { kind, data } => println!("#{} Unknown {} bytes (Kind: {})", index, data.len(), kind)
+    }
+}
+
+fn decode(input: &str) -> anyhow::Result<()> {
+    let bytes = if fs::exists(input).unwrap_or(false) {
+        fs::read(input)?
+    } else {
+        let input: String = input.trim_start_matches("0x").chars().filter(|char| !char.is_whitespace()).collect();
+        hex::decode(input).map_err(|error| anyhow::anyhow!("Input is neither a file nor a valid hex string ({})", error))?
+    };
+
+    let (remaining, messages) = BGPMessage::unpack_many(&bytes).unwrap_or((&bytes, Vec::new()));
+    messages.iter().enumerate().for_each(|(index, message)| print_message(index, message));
+    if !remaining.is_empty() {
+        let diagnostic = BGPMessage::unpack_diagnostic(remaining).err().map(|diagnostic| diagnostic.to_string()).unwrap_or_default();
+        return Err(anyhow::anyhow!(
+            "Decoded {} messages, but unable to decode the remaining {} bytes at offset {} => {}",
+            messages.len(),
+            remaining.len(),
+            bytes.len() - remaining.len(),
+            diagnostic
+        ));
+    }
+    Ok(())
+}
+
 #[rocket::main]
 async fn main() {
+    let arguments = Arguments::parse();
     if let Err(error) = SimpleLogger::new().with_level(LevelFilter::Info).init() {
         println!("Unable to initialize logging => {}", error);
         exit(-1);
     }
 
+    if let Some(Command::Decode { input }) = arguments.command {
+        if let Err(error) = decode(&input) {
+            error!("Unable to decode BGP messages => {}", error);
+            exit(-1);
+        }
+        return;
+    }
+
     let header = r#"   ____       __              _
   / __ \_____/ /_____ __   __(_)_  _______
  / / / / ___/ __/ __ `/ | / / / / / / ___/
@@ -48,13 +133,68 @@ async fn main() {
     println!("{}\n", header.gradient(Color::Green).bold());
 
     let database_url = "sqlite://database.db"; // TODO: replace with configuration file (if no config, wizard on start of the router)
-    let database = connect_database(&database_url).await;
+    let database = connect_database(database_url).await;
     if let Err(error) = database {
         error!("Unable to establish connection to database => {}", error);
         exit(-1);
     }
 
     let database = database.unwrap();
-    // TODO: Run migration for all tables
-    // TODO: (Only if no users present) Create user database
+    if let Err(error) = database::migrate(&database).await {
+        error!("Unable to migrate database => {}", error);
+        exit(-1);
+    }
+
+    match database::create_admin_user(&database).await {
+        Ok(Some(password)) => {
+            // The password is printed once to stderr outside the logger, so it isn't persisted with the logs
+            eprintln!("Generated password of user 'admin': {}", password);
+            warn!("Created user 'admin' with a generated password, please change it after the first login");
+        },
+        Ok(None) => {},
+        Err(error) => {
+            error!("Unable to create admin user => {}", error);
+            exit(-1);
+        }
+    }
+
+    let router_config = match database::load_router_config(&database).await {
+        Ok((router_config, created)) => {
+            if created {
+                warn!("Created router config with AS{} and router ID {}, please change it before adding peers", router_config.local_asn,
+                      router_config.router_id);
+            }
+            router_config
+        },
+        Err(error) => {
+            error!("Unable to load router config => {}", error);
+            exit(-1);
+        }
+    };
+
+    // TODO: Pass the open message template to the BGP server when it is implemented
+    let open_message = router_config.open_message(DEFAULT_HOLD_TIME);
+    if let Err(error) = open_message {
+        error!("Invalid router config => {}", error);
+        exit(-1);
+    }
+
+    let open_message = open_message.unwrap();
+    info!("Loaded router config (AS{}, Router ID {}, Listening on {})", router_config.local_asn, open_message.bgp_identifier_ip(),
+          router_config.listen_addresses);
+
+    let peers = database::peer::Entity::find().filter(database::peer::Column::Enabled.eq(true)).all(&database).await;
+    if let Err(error) = peers {
+        error!("Unable to load BGP peers from database => {}", error);
+        exit(-1);
+    }
+
+    // TODO: Create a session for each peer when the BGP server is implemented
+    let peers = peers.unwrap();
+    info!("Loaded {} enabled BGP peers from database", peers.len());
+
+    if let Err(error) = rocket::build().manage(database).mount("/", web::routes()).launch().await {
+        error!("Unable to launch web interface => {}", error);
+        exit(-1);
+    }
 }
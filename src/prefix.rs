@@ -1,7 +1,14 @@
+pub mod trie;
+
+#[cfg(test)]
+pub mod tests;
+
+use std::cmp::{min, Ordering};
 use std::fmt::{Display, Formatter};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 use nom::bytes::complete::take;
+use nom::error::{Error, ErrorKind};
 use nom::IResult;
 use nom::number::complete::be_u8;
 use crate::protocols::bgp::rfc4760::AddressFamily;
@@ -9,7 +16,7 @@ use crate::protocols::bgp::unpack_address;
 
 /// This enum implements support for serializing IPv4 and IPv6 prefixes from binary or text data. Prefixes are used to address a part of a
 /// network like the Internet.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Prefix {
     address: IpAddr,
     mask: u8
@@ -24,17 +31,252 @@ impl Display for Prefix {
 impl FromStr for Prefix {
     type Err = anyhow::Error;
     
+    /// This function parses a prefix in the `<address>/<mask>` syntax. Bare addresses are accepted as host prefixes with a mask of 32 bits
+    /// (IPv4) or 128 bits (IPv6).
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        let (address, mask) = str.split_once("/").ok_or(anyhow::anyhow!("Invalid prefix syntax"))?;
+        let Some((address, mask)) = str.split_once("/") else {
+            let address = IpAddr::from_str(str).map_err(|_| anyhow::anyhow!("Invalid prefix syntax"))?;
+            let mask = if address.is_ipv4() { 32 } else { 128 };
+            return Ok(Self { address, mask });
+        };
         let (address, mask) = (IpAddr::from_str(address)?, u8::from_str(mask)?);
         Ok(Self { address, mask })
     }
 }
 
 impl Prefix {
+    /// The IPv4 default route (`0.0.0.0/0`) covering all IPv4 addresses
+    pub const ANY_IPV4: Prefix = Prefix { address: IpAddr::V4(Ipv4Addr::UNSPECIFIED), mask: 0 };
+
+    /// The IPv6 default route (`::/0`) covering all IPv6 addresses
+    pub const ANY_IPV6: Prefix = Prefix { address: IpAddr::V6(Ipv6Addr::UNSPECIFIED), mask: 0 };
+
+    /// The maximum count of host bits a prefix can have to enumerate its addresses with [Prefix::hosts]
+    pub const MAX_HOST_BITS: u8 = 32;
+
+    pub fn new(address: IpAddr, mask: u8) -> Self {
+        Self { address, mask }
+    }
+
+    /// This function creates a prefix with the host bits of the address cleared. If the mask exceeds the width of the address family (32
+    /// bits for IPv4 and 128 bits for IPv6), this function returns an error.
+    pub fn new_canonical(address: IpAddr, mask: u8) -> anyhow::Result<Self> {
+        let prefix = Self { address, mask };
+        if mask > prefix.width() {
+            return Err(anyhow::anyhow!("Mask /{} exceeds the width of {} bits of address {}", mask, prefix.width(), address));
+        }
+        Ok(Self { address: prefix.network(), mask })
+    }
+
+    #[inline(always)]
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// This function returns the address family of the prefix, so callers can branch on the family without matching the address.
+    pub fn family(&self) -> AddressFamily {
+        match self.address {
+            IpAddr::V4(_) => AddressFamily::IPv4,
+            IpAddr::V6(_) => AddressFamily::IPv6
+        }
+    }
+
+    /// This function returns the network address of the prefix, which is the address with all host bits cleared.
+    pub fn network(&self) -> IpAddr {
+        self.address_from_bits(self.network_bits())
+    }
+
+    /// This function returns the broadcast address of the prefix, which is the address with all host bits set. Only IPv4 has broadcast
+    /// addresses, so this function returns none for IPv6 prefixes.
+    pub fn broadcast(&self) -> Option<IpAddr> {
+        match self.address {
+            IpAddr::V4(_) => Some(self.address_from_bits(self.address_bits() | self.host_mask())),
+            IpAddr::V6(_) => None
+        }
+    }
+
+    /// This function returns an iterator over all addresses covered by this prefix, including the network and broadcast address. If the
+    /// prefix has more than [Prefix::MAX_HOST_BITS] host bits (like `::/0`), the iterator is empty as enumerating that space is impractical.
+    pub fn hosts(&self) -> impl Iterator<Item = IpAddr> {
+        let prefix = *self;
+        let count = if self.host_bits() > Self::MAX_HOST_BITS { 0 } else { 1u128 << self.host_bits() };
+        let network = self.network_bits();
+        (0..count).map(move |index| prefix.address_from_bits(network + index))
+    }
+
+    /// This function compares two prefixes by their specificity. The prefixes are ordered by address family (IPv4 before IPv6), then by
+    /// network address and more-specific prefixes are ordered before less-specific prefixes with the same network address. This ordering
+    /// is used when scanning a RIB for the longest prefix match.
+    pub fn by_specificity(&self, other: &Self) -> Ordering {
+        self.address.is_ipv6().cmp(&other.address.is_ipv6())
+            .then(self.network_bits().cmp(&other.network_bits()))
+            .then(other.mask.cmp(&self.mask))
+    }
+
+    /// This function returns the covering supernet of this prefix and the other prefix, if both prefixes have the same address family and
+    /// length and are the two halves of the supernet.
+    fn supernet_with(&self, other: &Self) -> Option<Self> {
+        if self.address.is_ipv6() != other.address.is_ipv6() || self.mask != other.mask || self.mask == 0 || self.mask > self.width() {
+            return None;
+        }
+
+        let bit = 1u128 << (self.width() - self.mask);
+        if self.network_bits() & bit != 0 || other.network_bits() != self.network_bits() | bit {
+            return None;
+        }
+        Some(Self { address: self.network(), mask: self.mask - 1 })
+    }
+
+    /// This function returns the bit of the address at the specified index, counted from the most significant bit.
+    fn bit(&self, index: u8) -> usize {
+        ((self.address_bits() >> (self.width() - 1 - index)) & 1) as usize
+    }
+
+    fn network_bits(&self) -> u128 {
+        self.address_bits() & !self.host_mask()
+    }
+
+    fn width(&self) -> u8 {
+        match self.address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128
+        }
+    }
+
+    fn host_bits(&self) -> u8 {
+        self.width().saturating_sub(self.mask)
+    }
+
+    fn host_mask(&self) -> u128 {
+        u128::MAX.checked_shr(128 - self.host_bits() as u32).unwrap_or(0)
+    }
+
+    fn address_bits(&self) -> u128 {
+        match self.address {
+            IpAddr::V4(address) => address.to_bits() as u128,
+            IpAddr::V6(address) => address.to_bits()
+        }
+    }
+
+    fn address_from_bits(&self, bits: u128) -> IpAddr {
+        match self.address {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from_bits(bits as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from_bits(bits))
+        }
+    }
+
+    /// This function reads a prefix from the input bytes. Bits of the address beyond the mask are cleared, as some peers don't zero the
+    /// trailing bits of the last byte. Masks exceeding the width of the address are rejected, as such prefixes can't be packed again.
     pub(crate) fn unpack(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], Self> {
-        let (input, mask) = be_u8(input)?;
-        let (input, prefix) = take((mask + 7) / 8)(input)?;
-        Ok((input, Prefix { address: unpack_address(prefix, address_family)?.1, mask }))
+        let (input, prefix) = Self::unpack_raw(input, address_family)?;
+        Ok((input, Prefix { address: prefix.network(), mask: prefix.mask }))
+    }
+
+    /// This function reads a prefix from the input bytes like [Prefix::unpack], but fails if the trailing bits of the last byte beyond the
+    /// mask aren't zeroed as required by RFC 4271.
+    ///
+    /// ## References
+    /// - [UPDATE Message Format, Section 4.3 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.3)
+    pub fn unpack_strict(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], Self> {
+        let (remaining, prefix) = Self::unpack_raw(input, address_family)?;
+        if prefix.network() != prefix.address {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        Ok((remaining, prefix))
+    }
+
+    fn unpack_raw(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], Self> {
+        let (remaining, mask) = be_u8(input)?;
+        let (remaining, prefix) = take(mask.div_ceil(8))(remaining)?;
+        let prefix = Prefix { address: unpack_address(prefix, address_family)?.1, mask };
+        if prefix.mask > prefix.width() {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        Ok((remaining, prefix))
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.pack_into(&mut bytes);
+        bytes
+    }
+
+    /// This function returns the length of the serialized prefix, which is the mask followed by the significant bytes of the address.
+    pub(crate) fn wire_len(&self) -> usize {
+        1 + min(self.mask.div_ceil(8) as usize, self.width() as usize / 8)
+    }
+
+    pub(crate) fn pack_into(&self, buffer: &mut Vec<u8>) {
+        let length = min(self.mask.div_ceil(8) as usize, self.width() as usize / 8);
+        buffer.push(self.mask);
+        match self.address {
+            IpAddr::V4(address) => buffer.extend_from_slice(&address.octets()[..length]),
+            IpAddr::V6(address) => buffer.extend_from_slice(&address.octets()[..length])
+        }
+    }
+}
+
+/// This implementation generates canonical prefixes of both address families from the unstructured input of fuzzers (like `cargo fuzz`)
+/// and the property tests of the parsers, the trie and the aggregation. The mask is always within the width of the address family and
+/// the host bits of the address are cleared.
+#[cfg(any(test, feature = "arbitrary"))]
+impl<'a> arbitrary::Arbitrary<'a> for Prefix {
+    fn arbitrary(unstructured: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let address = match unstructured.arbitrary::<bool>()? {
+            true => IpAddr::V4(Ipv4Addr::from_bits(unstructured.arbitrary()?)),
+            false => IpAddr::V6(Ipv6Addr::from_bits(unstructured.arbitrary()?))
+        };
+        let mask = unstructured.int_in_range(0..=if address.is_ipv4() { 32 } else { 128 })?;
+        Ok(Prefix { address: Prefix { address, mask }.network(), mask })
+    }
+}
+
+/// This function returns the most specific prefix covering both prefixes, which is determined by the common leading bits of the network
+/// addresses. If the prefixes have different address families, this function returns none. This is used to find the branching point of
+/// two prefixes when building the prefix trie or aggregating routes.
+pub fn common_ancestor(a: &Prefix, b: &Prefix) -> Option<Prefix> {
+    if a.address.is_ipv6() != b.address.is_ipv6() {
+        return None;
+    }
+
+    let common_bits = ((a.network_bits() ^ b.network_bits()).leading_zeros() - (128 - a.width() as u32)) as u8;
+    let mask = min(common_bits, min(a.mask, b.mask)).min(a.width());
+    Some(Prefix { address: Prefix { address: a.address, mask }.network(), mask })
+}
+
+/// This function summarizes the specified prefixes by merging two adjacent prefixes with the same length into their covering supernet,
+/// until no more prefixes can be merged. Prefixes of different address families are never merged, and overlapping or non-adjacent
+/// prefixes are left intact (only exact duplicates are removed). The aggregated prefixes are returned ordered by [Prefix::by_specificity].
+pub fn aggregate(prefixes: &[Prefix]) -> Vec<Prefix> {
+    let mut prefixes: Vec<Prefix> = prefixes.iter().map(|prefix| Prefix { address: prefix.network(), mask: prefix.mask }).collect();
+    loop {
+        // Prefixes with the same length are grouped, so the two halves of a supernet are always next to each other
+        prefixes.sort_by(|prefix, other| {
+            prefix.address.is_ipv6().cmp(&other.address.is_ipv6())
+                .then(other.mask.cmp(&prefix.mask))
+                .then(prefix.network_bits().cmp(&other.network_bits()))
+        });
+        prefixes.dedup();
+
+        let mut aggregated = Vec::with_capacity(prefixes.len());
+        let mut index = 0;
+        while index < prefixes.len() {
+            match prefixes.get(index + 1).and_then(|next| prefixes[index].supernet_with(next)) {
+                Some(supernet) => {
+                    aggregated.push(supernet);
+                    index += 2;
+                },
+                None => {
+                    aggregated.push(prefixes[index]);
+                    index += 1;
+                }
+            }
+        }
+
+        if aggregated.len() == prefixes.len() {
+            aggregated.sort_by(Prefix::by_specificity);
+            return aggregated;
+        }
+        prefixes = aggregated;
     }
 }
@@ -0,0 +1,216 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use arbitrary::Unstructured;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::prefix::{aggregate, common_ancestor, Prefix};
+use crate::prefix::trie::PrefixTrie;
+use crate::protocols::bgp::rfc4760::AddressFamily;
+
+#[test]
+fn prefix_hosts() {
+    let prefix = Prefix::from_str("192.168.1.0/30").unwrap();
+    let hosts: Vec<IpAddr> = prefix.hosts().collect();
+    let expected: Vec<IpAddr> = ["192.168.1.0", "192.168.1.1", "192.168.1.2", "192.168.1.3"].iter()
+        .map(|address| IpAddr::from_str(address).unwrap())
+        .collect();
+    assert_eq!(expected, hosts);
+    assert_eq!(0, Prefix::from_str("::/0").unwrap().hosts().count());
+    assert_eq!(1, Prefix::from_str("2001:db8::1/128").unwrap().hosts().count());
+}
+
+#[test]
+fn prefix_network_and_broadcast() {
+    let prefix = Prefix::from_str("10.1.2.3/16").unwrap();
+    assert_eq!(IpAddr::from_str("10.1.0.0").unwrap(), prefix.network());
+    assert_eq!(Some(IpAddr::from_str("10.1.255.255").unwrap()), prefix.broadcast());
+
+    let prefix = Prefix::from_str("2001:db8::1/32").unwrap();
+    assert_eq!(IpAddr::from_str("2001:db8::").unwrap(), prefix.network());
+    assert_eq!(None, prefix.broadcast());
+}
+
+#[test]
+fn unpack_prefix_with_trailing_bits() {
+    let bytes = [20, 10, 0, 0x1F];
+    assert_eq!(Prefix::from_str("10.0.16.0/20").unwrap(), Prefix::unpack(&bytes, AddressFamily::IPv4).unwrap().1);
+    assert!(Prefix::unpack_strict(&bytes, AddressFamily::IPv4).is_err());
+    assert!(Prefix::unpack_strict(&[20, 10, 0, 0x10], AddressFamily::IPv4).is_ok());
+    assert!(Prefix::unpack_strict(&[33, 10, 0, 0, 0, 0], AddressFamily::IPv4).is_err());
+}
+
+#[test]
+fn unpack_prefix_with_mask_exceeding_width() {
+    // An IPv4 prefix with a mask of 40 bits can't be packed again, so it is rejected by the lenient parser too
+    assert!(Prefix::unpack(&[40, 10, 0, 0, 0, 0], AddressFamily::IPv4).is_err());
+    assert!(Prefix::unpack_strict(&[40, 10, 0, 0, 0, 0], AddressFamily::IPv4).is_err());
+    assert!(Prefix::unpack(&[129, 0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], AddressFamily::IPv6).is_err());
+
+    let prefix = Prefix::unpack(&[32, 10, 0, 0, 1], AddressFamily::IPv4).unwrap().1;
+    assert_eq!(vec![32, 10, 0, 0, 1], prefix.pack());
+}
+
+#[test]
+fn prefix_ordering_by_specificity() {
+    let mut prefixes: Vec<Prefix> = ["2001:db8::/32", "10.0.0.0/8", "10.0.1.0/24", "10.0.0.0/24", "10.0.0.0/16"].iter()
+        .map(|prefix| Prefix::from_str(prefix).unwrap())
+        .collect();
+    prefixes.sort_by(Prefix::by_specificity);
+
+    let prefixes: Vec<String> = prefixes.iter().map(Prefix::to_string).collect();
+    assert_eq!(vec!["10.0.0.0/24", "10.0.0.0/16", "10.0.0.0/8", "10.0.1.0/24", "2001:db8::/32"], prefixes);
+}
+
+#[test]
+fn default_route_round_trip() {
+    assert_eq!(Prefix::from_str("0.0.0.0/0").unwrap(), Prefix::ANY_IPV4);
+    assert_eq!(Prefix::from_str("::/0").unwrap(), Prefix::ANY_IPV6);
+    for (prefix, address_family) in [(Prefix::ANY_IPV4, AddressFamily::IPv4), (Prefix::ANY_IPV6, AddressFamily::IPv6)] {
+        assert_eq!(vec![0], prefix.pack());
+        assert_eq!((&[][..], prefix), Prefix::unpack(&[0], address_family).unwrap());
+    }
+}
+
+fn aggregate_strings(prefixes: &[&str]) -> Vec<String> {
+    let prefixes: Vec<Prefix> = prefixes.iter().map(|prefix| Prefix::from_str(prefix).unwrap()).collect();
+    aggregate(&prefixes).iter().map(Prefix::to_string).collect()
+}
+
+#[test]
+fn aggregate_adjacent_prefixes() {
+    assert_eq!(vec!["10.0.0.0/24"], aggregate_strings(&["10.0.0.0/25", "10.0.0.128/25"]));
+    assert_eq!(vec!["10.0.0.0/22"], aggregate_strings(&["10.0.3.0/24", "10.0.1.0/24", "10.0.0.0/24", "10.0.2.0/24"]));
+    assert_eq!(vec!["2001:db8::/32"], aggregate_strings(&["2001:db8::/33", "2001:db8:8000::/33"]));
+}
+
+#[test]
+fn aggregate_non_adjacent_prefixes() {
+    // Both prefixes are next to each other, but aren't the two halves of the same supernet
+    assert_eq!(vec!["10.0.1.0/24", "10.0.2.0/24"], aggregate_strings(&["10.0.1.0/24", "10.0.2.0/24"]));
+    assert_eq!(vec!["10.0.0.0/25", "10.0.0.0/24"], aggregate_strings(&["10.0.0.0/24", "10.0.0.0/25"]));
+    assert_eq!(vec!["10.0.0.0/24", "10.0.2.0/24"], aggregate_strings(&["10.0.0.0/24", "10.0.2.0/24"]));
+}
+
+#[test]
+fn aggregate_address_families_isolated() {
+    assert_eq!(vec!["0.0.0.0/1", "::/1"], aggregate_strings(&["0.0.0.0/1", "::/1"]));
+    assert_eq!(vec!["0.0.0.0/0", "::/1"], aggregate_strings(&["128.0.0.0/1", "::/1", "0.0.0.0/1"]));
+}
+
+#[test]
+fn prefix_from_bare_address() {
+    assert_eq!(Prefix::from_str("10.0.0.1/32").unwrap(), Prefix::from_str("10.0.0.1").unwrap());
+    assert_eq!(Prefix::from_str("2001:db8::1/128").unwrap(), Prefix::from_str("2001:db8::1").unwrap());
+    assert_eq!("10.0.0.1/32", Prefix::from_str("10.0.0.1").unwrap().to_string());
+    assert!(Prefix::from_str("192.0.2.1/").is_err());
+    assert!(Prefix::from_str("192.0.2").is_err());
+}
+
+#[test]
+fn prefix_family_and_canonical_mask() {
+    let prefix = Prefix::new_canonical(IpAddr::from_str("192.0.2.1").unwrap(), 32).unwrap();
+    assert_eq!(AddressFamily::IPv4, prefix.family());
+    assert_eq!("192.0.2.1/32", prefix.to_string());
+    assert!(Prefix::new_canonical(IpAddr::from_str("192.0.2.1").unwrap(), 33).is_err());
+
+    let prefix = Prefix::new_canonical(IpAddr::from_str("2001:db8::1").unwrap(), 128).unwrap();
+    assert_eq!(AddressFamily::IPv6, prefix.family());
+    assert_eq!("2001:db8::1/128", prefix.to_string());
+    assert!(Prefix::new_canonical(IpAddr::from_str("2001:db8::1").unwrap(), 129).is_err());
+    assert_eq!("2001:db8::/32", Prefix::new_canonical(IpAddr::from_str("2001:db8::1").unwrap(), 32).unwrap().to_string());
+}
+
+#[test]
+fn trie_longest_match() {
+    let mut trie = PrefixTrie::new();
+    for (index, prefix) in ["0.0.0.0/0", "10.0.0.0/8", "10.1.0.0/16", "10.1.2.0/24", "2001:db8::/32"].into_iter().enumerate() {
+        assert_eq!(None, trie.insert(Prefix::from_str(prefix).unwrap(), index));
+    }
+    assert_eq!(Some(1), trie.insert(Prefix::from_str("10.0.0.0/8").unwrap(), 1));
+    assert_eq!(5, trie.len());
+
+    let longest_match = |address: &str| {
+        trie.longest_match(IpAddr::from_str(address).unwrap()).map(|(prefix, value)| (prefix.to_string(), *value))
+    };
+    assert_eq!(Some(("10.1.2.0/24".to_string(), 3)), longest_match("10.1.2.3"));
+    assert_eq!(Some(("10.1.0.0/16".to_string(), 2)), longest_match("10.1.3.1"));
+    assert_eq!(Some(("10.0.0.0/8".to_string(), 1)), longest_match("10.2.0.1"));
+    assert_eq!(Some(("0.0.0.0/0".to_string(), 0)), longest_match("192.0.2.1"));
+    assert_eq!(Some(("2001:db8::/32".to_string(), 4)), longest_match("2001:db8::1"));
+    assert_eq!(None, longest_match("2001:db9::1"));
+
+    let mut trie = trie.clone();
+    assert_eq!(Some(2), trie.remove(&Prefix::from_str("10.1.0.0/16").unwrap()));
+    assert_eq!(None, trie.remove(&Prefix::from_str("10.1.0.0/16").unwrap()));
+    let (prefix, _) = trie.longest_match(IpAddr::from_str("10.1.3.1").unwrap()).unwrap();
+    assert_eq!("10.0.0.0/8", prefix.to_string());
+    assert_eq!(Some(&3), trie.get(&Prefix::from_str("10.1.2.0/24").unwrap()));
+    assert_eq!(4, trie.len());
+}
+
+#[test]
+fn trie_many_prefixes() {
+    // 10000 /24 prefixes covering 10.0.0.0 - 10.39.15.255, whose lookups are compared against a linear scan
+    let prefixes: Vec<Prefix> = (0..10000u32)
+        .map(|index| Prefix::new(IpAddr::V4(Ipv4Addr::from_bits(0x0A000000 + (index << 8))), 24))
+        .collect();
+    let mut trie = PrefixTrie::new();
+    prefixes.iter().enumerate().for_each(|(index, prefix)| { trie.insert(*prefix, index); });
+    trie.insert(Prefix::from_str("10.0.0.0/8").unwrap(), usize::MAX);
+    assert_eq!(10001, trie.len());
+
+    for bits in (0x0A000001..0x0AFFFFFFu32).step_by(9973) {
+        let address = IpAddr::V4(Ipv4Addr::from_bits(bits));
+        let expected = prefixes.iter().position(|prefix| prefix.network() == Prefix::new(address, 24).network()).unwrap_or(usize::MAX);
+        assert_eq!(Some(expected), trie.longest_match(address).map(|(_, value)| *value));
+    }
+}
+
+/// This function returns the unstructured data for the property tests, which is generated from a fixed seed to keep the tests reproducible
+fn seeded_data(length: usize) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(0x0C7A);
+    (0..length).map(|_| rng.random()).collect()
+}
+
+#[test]
+fn arbitrary_prefixes_are_canonical() {
+    let data = seeded_data(16384);
+    let mut unstructured = Unstructured::new(&data);
+    for _ in 0..1000 {
+        let prefix: Prefix = unstructured.arbitrary().unwrap();
+        assert_eq!(prefix, Prefix::new_canonical(prefix.network(), prefix.mask()).unwrap());
+        assert_eq!(Ok((&[][..], prefix)), Prefix::unpack_strict(&prefix.pack(), prefix.family()));
+    }
+}
+
+#[test]
+fn aggregate_is_idempotent() {
+    let data = seeded_data(131072);
+    let mut unstructured = Unstructured::new(&data);
+    for _ in 0..200 {
+        // Arbitrary prefixes are rarely adjacent, so they are mixed with prefixes of a small range to exercise the merging
+        let mut prefixes: Vec<Prefix> = (0..16).map(|_| unstructured.arbitrary().unwrap()).collect();
+        prefixes.extend((0..48).map(|_| {
+            let address = IpAddr::V4(Ipv4Addr::from_bits(0x0A000000 | (unstructured.int_in_range(0..=63u32).unwrap() << 8)));
+            Prefix::new_canonical(address, unstructured.int_in_range(22..=24).unwrap()).unwrap()
+        }));
+
+        let aggregated = aggregate(&prefixes);
+        assert!(aggregated.len() < prefixes.len());
+        assert_eq!(aggregated, aggregate(&aggregated));
+    }
+}
+
+#[test]
+fn common_ancestor_of_prefixes() {
+    let ancestor = |a: &str, b: &str| {
+        common_ancestor(&Prefix::from_str(a).unwrap(), &Prefix::from_str(b).unwrap()).map(|prefix| prefix.to_string())
+    };
+    assert_eq!(Some("10.0.0.0/23".to_string()), ancestor("10.0.0.0/24", "10.0.1.0/24"));
+    assert_eq!(Some("10.0.0.0/14".to_string()), ancestor("10.1.2.0/24", "10.2.0.0/16"));
+    assert_eq!(Some("10.0.0.0/8".to_string()), ancestor("10.0.0.0/8", "10.255.0.0/16"));
+    assert_eq!(Some("192.0.2.0/24".to_string()), ancestor("192.0.2.0/24", "192.0.2.0/24"));
+    assert_eq!(Some("0.0.0.0/0".to_string()), ancestor("10.0.0.0/8", "192.168.0.0/16"));
+    assert_eq!(Some("2001:db8::/32".to_string()), ancestor("2001:db8:1::/48", "2001:db8:8000::/48"));
+    assert_eq!(None, ancestor("10.0.0.0/8", "2001:db8::/32"));
+}
@@ -0,0 +1,147 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements a binary trie of prefixes, which is used to find the longest prefix match of an address without scanning all
+//! prefixes of a RIB. IPv4 and IPv6 prefixes are stored in separate tries, so prefixes of different address families never match.
+
+use std::net::IpAddr;
+use crate::prefix::Prefix;
+
+#[derive(Clone, Debug)]
+struct Node<V> {
+    children: [Option<Box<Node<V>>>; 2],
+    entry: Option<(Prefix, V)>
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Self { children: [None, None], entry: None }
+    }
+}
+
+impl<V> Node<V> {
+    /// This function removes the entry of the prefix from the subtree of this node and removes the nodes left without entries. The depth
+    /// is the count of bits of the prefix already consumed by the parents of this node.
+    fn remove(&mut self, prefix: &Prefix, depth: u8) -> Option<V> {
+        if depth == prefix.mask() {
+            return self.entry.take().map(|(_, value)| value);
+        }
+
+        let child = &mut self.children[prefix.bit(depth)];
+        let value = child.as_mut()?.remove(prefix, depth + 1);
+        if child.as_ref().is_some_and(|child| child.is_empty()) {
+            *child = None;
+        }
+        value
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entry.is_none() && self.children.iter().all(Option::is_none)
+    }
+}
+
+/// This struct maps prefixes to values and finds the most-specific prefix containing an address in a time proportional to the width of the
+/// address instead of the count of prefixes. The host bits of the inserted prefixes are cleared.
+#[derive(Clone, Debug)]
+pub struct PrefixTrie<V> {
+    ipv4: Node<V>,
+    ipv6: Node<V>,
+    len: usize
+}
+
+impl<V> Default for PrefixTrie<V> {
+    fn default() -> Self {
+        Self { ipv4: Node::default(), ipv6: Node::default(), len: 0 }
+    }
+}
+
+impl<V> PrefixTrie<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function inserts the value for the prefix and returns the previous value of the prefix, if the prefix was already present. If
+    /// the mask exceeds the width of the address family, the mask is limited to the width.
+    pub fn insert(&mut self, prefix: Prefix, value: V) -> Option<V> {
+        let prefix = Prefix::new(prefix.network(), prefix.mask().min(prefix.width()));
+        let mut node = self.root_mut(&prefix.network());
+        for depth in 0..prefix.mask() {
+            node = node.children[prefix.bit(depth)].get_or_insert_with(Box::default);
+        }
+
+        let previous = node.entry.replace((prefix, value)).map(|(_, value)| value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// This function removes the prefix and returns its value, if the prefix was present.
+    pub fn remove(&mut self, prefix: &Prefix) -> Option<V> {
+        let prefix = Prefix::new(prefix.network(), prefix.mask().min(prefix.width()));
+        let value = self.root_mut(&prefix.network()).remove(&prefix, 0);
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    /// This function returns the value of the prefix, if the prefix is present. Unlike [PrefixTrie::longest_match], only the exact prefix
+    /// is matched.
+    pub fn get(&self, prefix: &Prefix) -> Option<&V> {
+        let prefix = Prefix::new(prefix.network(), prefix.mask().min(prefix.width()));
+        let mut node = self.root(&prefix.network());
+        for depth in 0..prefix.mask() {
+            node = node.children[prefix.bit(depth)].as_deref()?;
+        }
+        node.entry.as_ref().map(|(_, value)| value)
+    }
+
+    /// This function returns the most-specific prefix containing the address with its value, or none if no prefix contains the address.
+    pub fn longest_match(&self, address: IpAddr) -> Option<(&Prefix, &V)> {
+        let host = Prefix::new(address, Prefix::new(address, 0).width());
+        let mut node = self.root(&address);
+        let mut longest_match = node.entry.as_ref();
+        for depth in 0..host.mask() {
+            let Some(child) = node.children[host.bit(depth)].as_deref() else {
+                break;
+            };
+            node = child;
+            longest_match = node.entry.as_ref().or(longest_match);
+        }
+        longest_match.map(|(prefix, value)| (prefix, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn root(&self, address: &IpAddr) -> &Node<V> {
+        match address {
+            IpAddr::V4(_) => &self.ipv4,
+            IpAddr::V6(_) => &self.ipv6
+        }
+    }
+
+    fn root_mut(&mut self, address: &IpAddr) -> &mut Node<V> {
+        match address {
+            IpAddr::V4(_) => &mut self.ipv4,
+            IpAddr::V6(_) => &mut self.ipv6
+        }
+    }
+}
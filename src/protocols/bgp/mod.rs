@@ -16,21 +16,31 @@
 //! be aware that not every RFCs is currently implemented into this codebase. Below this text you can see a list with the standards already
 //! implemented, not implemented or planned to be implemented.
 //!
-//! | RFC                                                       | Title                                      | Status      | File      |
-//! |-----------------------------------------------------------|--------------------------------------------|-------------|-----------|
-//! | [RFC 1997](https://datatracker.ietf.org/doc/html/rfc1997) | BGP Communities Attribute                  | Implemented | [rfc1997] |
-//! | [RFC 2918](https://datatracker.ietf.org/doc/html/rfc2918) | Route Refresh Capability for BGP-4         | Planned     | -/-       |
-//! | [RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392) | Capabilities Advertisement with BGP-4      | Implemented | [rfc3392] |
-//! | [RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271) | A Border Gateway Protocol 4 (BGP-4)        | Implemented | [self]    |
-//! | [RFC 4370](https://datatracker.ietf.org/doc/html/rfc4360) | BGP Extended Communities Attribute         | Implemented | [rfc1997] |
-//! | [RFC 4724](https://datatracker.ietf.org/doc/html/rfc4724) | Graceful Restart Mechanism for BGP         | Planned     | -/-       |
-//! | [RFC 4760](https://datatracker.ietf.org/doc/html/rfc4760) | Multiprotocol Extensions for BGP-4         | Implemented | [rfc4760] |
-//! | [RFC 5668](https://datatracker.ietf.org/doc/html/rfc5668) | 4-Octet AS-specific BGP Extended Community | Implemented | [rfc1997] |
-//! | [RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793) | BGP Support for Four-Octet AS Numbers      | Implemented | [rfc6793] |
-//! | [RFC 7313](https://datatracker.ietf.org/doc/html/rfc7313) | Enhanced Route Refresh Capability          | Planned     | -/-       |
-//! | [RFC 7606](https://datatracker.ietf.org/doc/html/rfc7606) | Revised Error Handling for BGP UPDATE      | Planned     | -/-       |
-//! | [RFC 8205](https://datatracker.ietf.org/doc/html/rfc8205) | BGPsec Protocol Specification              | Planned     | -/-       |
-//! | [RFC 8955](https://datatracker.ietf.org/doc/html/rfc8955) | Dissemination of FlowSpec rules            | Planned     | -/-       |
+//! | RFC                                                       | Title                                                    | Status      | File        |
+//! |-----------------------------------------------------------|----------------------------------------------------------|-------------|-------------|
+//! | [RFC 1997](https://datatracker.ietf.org/doc/html/rfc1997) | BGP Communities Attribute                                | Implemented | [rfc1997]   |
+//! | [RFC 2439](https://datatracker.ietf.org/doc/html/rfc2439) | BGP Route Flap Damping                                   | Implemented | [rfc2439]   |
+//! | [RFC 2918](https://datatracker.ietf.org/doc/html/rfc2918) | Route Refresh Capability for BGP-4                       | Planned     | -/-         |
+//! | [RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392) | Capabilities Advertisement with BGP-4                    | Implemented | [rfc3392]   |
+//! | [RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271) | A Border Gateway Protocol 4 (BGP-4)                      | Implemented | [self]      |
+//! | [RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364) | BGP/MPLS IP Virtual Private Networks (VPNs)              | Implemented | [rfc4364]   |
+//! | [RFC 4370](https://datatracker.ietf.org/doc/html/rfc4360) | BGP Extended Communities Attribute                       | Implemented | [rfc1997]   |
+//! | [RFC 4456](https://datatracker.ietf.org/doc/html/rfc4456) | BGP Route Reflection                                     | Implemented | [self]      |
+//! | [RFC 4724](https://datatracker.ietf.org/doc/html/rfc4724) | Graceful Restart Mechanism for BGP                       | Planned     | -/-         |
+//! | [RFC 4760](https://datatracker.ietf.org/doc/html/rfc4760) | Multiprotocol Extensions for BGP-4                       | Implemented | [rfc4760]   |
+//! | [RFC 5065](https://datatracker.ietf.org/doc/html/rfc5065) | Autonomous System Confederations for BGP                 | Implemented | [path_attr] |
+//! | [RFC 5668](https://datatracker.ietf.org/doc/html/rfc5668) | 4-Octet AS-specific BGP Extended Community               | Implemented | [rfc1997]   |
+//! | [RFC 6514](https://datatracker.ietf.org/doc/html/rfc6514) | BGP Encodings for Multicast in MPLS/BGP IP VPNs          | Implemented | [self]      |
+//! | [RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793) | BGP Support for Four-Octet AS Numbers                    | Implemented | [rfc6793]   |
+//! | [RFC 7313](https://datatracker.ietf.org/doc/html/rfc7313) | Enhanced Route Refresh Capability                        | Planned     | -/-         |
+//! | [RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432) | BGP MPLS-Based Ethernet VPN                              | Implemented | [rfc7432]   |
+//! | [RFC 7606](https://datatracker.ietf.org/doc/html/rfc7606) | Revised Error Handling for BGP UPDATE                    | Planned     | -/-         |
+//! | [RFC 8203](https://datatracker.ietf.org/doc/html/rfc8203) | BGP Administrative Shutdown Communication                | Implemented | [rfc8203]   |
+//! | [RFC 8205](https://datatracker.ietf.org/doc/html/rfc8205) | BGPsec Protocol Specification                            | Planned     | -/-         |
+//! | [RFC 8955](https://datatracker.ietf.org/doc/html/rfc8955) | Dissemination of FlowSpec rules                          | Planned     | -/-         |
+//! | [RFC 9012](https://datatracker.ietf.org/doc/html/rfc9012) | The BGP Tunnel Encapsulation Attribute                   | Implemented | [rfc9012]   |
+//! | [RFC 9072](https://datatracker.ietf.org/doc/html/rfc9072) | Extended Optional Parameters Length for BGP OPEN Message | Implemented | [params]    |
+//! | [RFC 9494](https://datatracker.ietf.org/doc/html/rfc9494) | Long-Lived Graceful Restart for BGP                      | Implemented | [rfc9494]   |
 //!
 //! The BGP (Border Gateway Protocol) is the EGP (Exterior Gateway Protocol) protocol used for the exchange of routes between two autonomous
 //! systems, but can also be used as an IGP (Interior Gateway Protocol) and is used for big networks. This module implements the processing
@@ -44,47 +54,219 @@ pub mod params;
 pub mod path_attr;
 
 pub mod rfc1997;
+pub mod rfc2439;
 pub mod rfc3392;
+pub mod rfc4364;
 pub mod rfc4760;
 pub mod rfc6793;
+pub mod rfc7432;
+pub mod rfc8203;
+#[cfg(feature = "rfc9012")]
+pub mod rfc9012;
+pub mod rfc9494;
+#[cfg(feature = "tokio")]
+pub mod stream;
 
 #[cfg(test)]
 pub mod tests;
 
-use std::cmp::min;
-use std::fmt::{Display, Formatter};
+use std::cmp::{min, Ordering};
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use bitflags::bitflags;
 use nom::bytes::complete::take;
+use nom::combinator::{all_consuming, verify};
 use nom::error::{Error, ErrorKind};
 use nom::{IResult, Parser};
 use nom::multi::{many0, many1};
 use nom::number::complete::{be_u16, be_u32, be_u8};
+#[cfg(feature = "rfc6514")]
+use nom::number::complete::be_u24;
 use crate::prefix::Prefix;
-use crate::protocols::bgp::params::OptionalParameter;
-use crate::protocols::bgp::path_attr::Origin;
+use crate::protocols::bgp::params::{OptionalParameter, ParamParseError};
+use crate::protocols::bgp::path_attr::{ASPathSegment, Origin};
 use crate::protocols::bgp::rfc1997::Community;
-use crate::protocols::bgp::rfc4760::{AddressFamily, MultiprotocolReachablePathAttribute, MultiprotocolUnreachablePathAttribute};
+use crate::protocols::bgp::rfc3392::Capability;
+use crate::protocols::bgp::rfc4760::{
+    AddressFamily, MultiprotocolNextHop, MultiprotocolReachablePathAttribute, MultiprotocolUnreachablePathAttribute,
+    SubsequentAddressFamily
+};
+use crate::protocols::bgp::rfc6793::AS4_PATH;
+use crate::protocols::bgp::rfc7432::EvpnRoute;
+#[cfg(feature = "rfc9012")]
+use crate::protocols::bgp::rfc9012::TunnelTlv;
 
 pub(crate) fn unpack_address(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], IpAddr> {
-    fn slice_to_array<const N: usize>(input: &[u8]) -> IResult<&[u8], [u8; N]> {
-        let mut array = [0u8; N];
-        let read = min(input.len(), N);
-        let (input, bytes) = take(read)(input)?;
-        array[0..read].copy_from_slice(bytes);
-        Ok((input, array))
-    }
-
-    match address_family {
-        AddressFamily::IPv4 => {
-            let (input, bytes) = slice_to_array::<4>(input)?;
-            Ok((input, IpAddr::V4(Ipv4Addr::from(bytes))))
-        },
-        AddressFamily::IPv6 => {
-            let (input, bytes) = slice_to_array::<16>(input)?;
-            Ok((input, IpAddr::V6(Ipv6Addr::from(bytes))))
+    let width = address_family.address_width().ok_or(nom::Err::Error(Error::new(input, ErrorKind::Complete)))?;
+    let mut array = [0u8; 16];
+    let read = min(input.len(), width);
+    let (input, bytes) = take(read)(input)?;
+    array[0..read].copy_from_slice(bytes);
+    Ok((input, match width {
+        4 => IpAddr::V4(Ipv4Addr::new(array[0], array[1], array[2], array[3])),
+        _ => IpAddr::V6(Ipv6Addr::from(array))
+    }))
+}
+
+/// The maximum length of a BGP message including the header
+///
+/// ## References
+/// - [Message Header Format, Section 4.1 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.1)
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// The maximum length of a BGP message including the header, if the extended message capability was negotiated in the session
+///
+/// ## References
+/// - [Changes to RFC 4271, Section 4 RFC 8654](https://datatracker.ietf.org/doc/html/rfc8654#section-4)
+pub const MAX_EXTENDED_MESSAGE_LENGTH: usize = 65535;
+
+/// The maximum count of path attributes accepted in a single update message
+pub const MAX_PATH_ATTRIBUTES: usize = 128;
+
+/// The maximum count of segments accepted in a single AS_PATH path attribute
+pub const MAX_AS_PATH_SEGMENTS: usize = 64;
+
+/// The maximum count of optional parameters accepted in a single open message
+pub const MAX_OPTIONAL_PARAMETERS: usize = 32;
+
+/// The maximum count of capabilities accepted in a single capabilities optional parameter
+pub const MAX_CAPABILITIES: usize = 128;
+
+/// This function applies the parser repeatedly like [many0] until the parser fails, but guarantees that every element consumes input and
+/// that no more than `maximum` elements are parsed. This protects against adversarial input driving the parser into endless loops or huge
+/// allocations.
+pub(crate) fn many_bounded<'a, O, F>(maximum: usize, mut parser: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+{
+    move |mut input: &'a [u8]| {
+        let mut elements = Vec::new();
+        while !input.is_empty() {
+            let (remaining, element) = match parser(input) {
+                Ok(result) => result,
+                Err(nom::Err::Error(_)) => break,
+                Err(error) => return Err(error)
+            };
+
+            if remaining.len() == input.len() {
+                return Err(nom::Err::Error(Error::new(input, ErrorKind::Many0)));
+            }
+            if elements.len() == maximum {
+                return Err(nom::Err::Error(Error::new(input, ErrorKind::TooLarge)));
+            }
+            elements.push(element);
+            input = remaining;
+        }
+        Ok((input, elements))
+    }
+}
+
+/// This function returns the position of the remaining input of a failed parser relative to the start of the input. All parsers operate on
+/// sub-slices of the input, so the position of the failure can be calculated from the address of the remaining input.
+fn error_offset(input: &[u8], remaining: &[u8]) -> usize {
+    (remaining.as_ptr() as usize).checked_sub(input.as_ptr() as usize)
+        .filter(|offset| *offset <= input.len())
+        .unwrap_or(input.len() - remaining.len().min(input.len()))
+}
+
+/// This function runs the nom parser of an element on the input and converts the result of the parser into the result returned by
+/// [BGPElement::parse].
+pub(crate) fn parse_element<'a, T>(input: &'a [u8], parser: impl FnOnce(&'a [u8]) -> IResult<&'a [u8], T>)
+    -> Result<(T, &'a [u8]), BgpParseError> {
+    match parser(input) {
+        Ok((remaining, element)) => Ok((element, remaining)),
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+            let offset = error_offset(input, error.input);
+            Err(match error.code {
+                ErrorKind::Eof | ErrorKind::Complete => BgpParseError::Truncated { offset },
+                _ => BgpParseError::BadTag { offset }
+            })
         },
-        _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Complete)))
+        Err(nom::Err::Incomplete(_)) => Err(BgpParseError::Truncated { offset: input.len() })
+    }
+}
+
+/// This struct wraps raw data for debug printing. With the alternate flag (`{:#?}`), the data is printed as hex string in groups of two
+/// bytes (like `0a0b 0c0d`) instead of a list of decimal bytes, which is unreadable for large payloads.
+pub(crate) struct HexDump<'a>(pub &'a [u8]);
+
+impl Debug for HexDump<'_> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        if !formatter.alternate() {
+            return Debug::fmt(self.0, formatter);
+        }
+
+        let groups: Vec<String> = self.0.chunks(2).map(hex::encode).collect();
+        write!(formatter, "{}", groups.join(" "))
+    }
+}
+
+/// This function encodes the value as JSON string with the quotes, escaping the quotes, backslashes and control characters of the value.
+fn json_string(value: &str) -> String {
+    let mut string = String::with_capacity(value.len() + 2);
+    string.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            char if char.is_control() => string.push_str(&format!("\\u{:04x}", char as u32)),
+            char => string.push(char)
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// This function encodes the values as JSON array of strings, using the [Display] form of each value.
+fn json_array<T: Display>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(|value| json_string(&value.to_string())).collect();
+    format!("[{}]", values.join(","))
+}
+
+pub(crate) fn pack_address(address: &IpAddr) -> Vec<u8> {
+    match address {
+        IpAddr::V4(address) => address.octets().to_vec(),
+        IpAddr::V6(address) => address.octets().to_vec()
+    }
+}
+
+/// This struct represents the header in front of every BGP message with the marker, the length of the whole message (including the
+/// header) and the type of the message. The header can be read without the body, so messages can be routed without being decoded.
+///
+/// ## References
+/// - [Message Header Format, Section 4.1 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.1)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub struct BGPMessageHeader {
+    pub marker: [u8; 16],
+    pub length: u16,
+    pub kind: u8
+}
+
+impl BGPMessageHeader {
+    /// The length of the header including the 16 bytes of the marker
+    pub const LENGTH: usize = 19;
+
+    /// The all-ones marker required by RFC 4271, which replaced the authentication data of the marker in earlier versions of the BGP
+    pub const STANDARD_MARKER: [u8; 16] = [0xFF; 16];
+
+    /// This function reads the header from the input and returns the remaining input starting with the body of the message. The length
+    /// must be at least the length of the header and the marker must be the [BGPMessageHeader::STANDARD_MARKER].
+    pub fn peek(input: &[u8]) -> IResult<&[u8], Self> {
+        Self::peek_with(input, true)
+    }
+
+    /// This function reads the header like [BGPMessageHeader::peek], but accepts any marker if the standard marker isn't required. This is
+    /// used for the interoperability with older implementations, which still send markers left over from the authentication.
+    pub fn peek_with(input: &[u8], require_standard_marker: bool) -> IResult<&[u8], Self> {
+        let (input, marker) = verify(take(16usize), |marker: &[u8]| !require_standard_marker || marker == Self::STANDARD_MARKER)
+            .parse(input)?;
+        let (input, length) = verify(be_u16, |length| *length as usize >= Self::LENGTH).parse(input)?;
+        let (input, kind) = be_u8(input)?;
+        Ok((input, Self { marker: marker.try_into().unwrap(), length, kind }))
     }
 }
 
@@ -101,27 +283,419 @@ pub enum BGPMessage {
 
 impl BGPMessage {
     pub fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
-        let (input, _marker) = take(16usize)(input)?;
-        let (input, length) = be_u16(input)?;
-        let (input, kind) = be_u8(input)?;
-        let (input, data) = take((length as usize) - 19)(input)?;
+        Self::unpack_with_marker(input, true)
+    }
+
+    /// This function parses a single message like [BGPMessage::unpack], but accepts any marker in the header if the standard marker isn't
+    /// required. The marker of the message can be read with [BGPMessageHeader::peek_with].
+    pub fn unpack_with_marker(input: &[u8], require_standard_marker: bool) -> IResult<&[u8], Self> {
+        Self::unpack_message(input, require_standard_marker, &SessionContext::four_octet_asn())
+    }
+
+    /// This function parses a single message like [BGPMessage::unpack], but with the maximum length of the message and the encoding of
+    /// update messages determined by the capabilities negotiated in the session.
+    pub fn unpack_with<'a>(input: &'a [u8], context: &SessionContext) -> IResult<&'a [u8], Self> {
+        Self::unpack_message(input, true, context)
+    }
+
+    fn unpack_message<'a>(input: &'a [u8], require_standard_marker: bool, context: &SessionContext) -> IResult<&'a [u8], Self> {
+        let (input, BGPMessageHeader { length, kind, .. }) = BGPMessageHeader::peek_with(input, require_standard_marker)?;
+        if length as usize > context.max_message_length() {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::TooLarge)));
+        }
+
+        let (input, data) = take(length as usize - BGPMessageHeader::LENGTH)(input)?;
         Ok((input, match kind {
             1 => Self::Open(OpenMessage::unpack(data)?.1),
-            2 => Self::Update(UpdateMessage::unpack(data)?.1),
+            2 => Self::Update(UpdateMessage::unpack_with(data, context)?.1),
             3 => Self::Notification(NotificationMessage::unpack(data)?.1),
             4 => Self::KeepAlive,
             _ => Self::Unknown { kind, data: data.to_vec() }
         }))
     }
 
+    /// This function parses a single message like [BGPMessage::unpack], but returns a diagnostic with the position of the failure in the
+    /// input if the message can't be parsed. This is used to debug messages of peers, which this implementation is unable to decode.
+    pub fn unpack_diagnostic(input: &[u8]) -> Result<Self, DecodeDiagnostic> {
+        match Self::unpack(input) {
+            Ok((_, message)) => Ok(message),
+            Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+                Err(DecodeDiagnostic::new(input, error_offset(input, error.input), error.code))
+            },
+            Err(nom::Err::Incomplete(_)) => Err(DecodeDiagnostic::new(input, input.len(), ErrorKind::Eof))
+        }
+    }
+
+    /// This function validates the length fields of the update message at the start of the input with
+    /// [UpdateMessage::validate_length_fields] before the message is decoded, so a peer declaring more bytes than sent is answered with the
+    /// matching NOTIFICATION. Other messages and messages shorter than the length in the header are left to the parser.
+    pub fn validate_update(input: &[u8]) -> Result<(), UpdateError> {
+        let Ok((body, BGPMessageHeader { length, kind: 2, .. })) = BGPMessageHeader::peek(input) else {
+            return Ok(());
+        };
+
+        match body.get(..length as usize - BGPMessageHeader::LENGTH) {
+            Some(body) => UpdateMessage::validate_length_fields(body),
+            None => Ok(())
+        }
+    }
+
+    /// This function returns the type of the message at the start of the input without decoding the message, or none if the input is
+    /// shorter than the header.
+    pub fn message_type(input: &[u8]) -> Option<u8> {
+        input.get(BGPMessageHeader::LENGTH - 1).copied()
+    }
+
     #[inline(always)]
     pub fn unpack_many(input: &[u8]) -> IResult<&[u8], Vec<Self>> {
         many1(Self::unpack).parse(input)
     }
+
+    /// This function serializes the message into the bytes sent over the wire. The message is prefixed with the BGP header containing the
+    /// all-ones marker, the length of the whole message and the message type.
+    pub fn pack(&self) -> Vec<u8> {
+        self.pack_with(&SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the message like [BGPMessage::pack], but update messages are encoded with the capabilities negotiated in
+    /// the session. This is the counterpart of [BGPMessage::unpack_with].
+    pub fn pack_with(&self, context: &SessionContext) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.pack_into_with(&mut bytes, context);
+        bytes
+    }
+
+    /// This function returns the length of the serialized message including the header without serializing the message, so buffers can
+    /// be sized up front. The length always matches the length of the bytes returned by [BGPMessage::pack].
+    pub fn wire_len(&self) -> usize {
+        BGPMessageHeader::LENGTH + match self {
+            Self::Open(open) => open.wire_len(),
+            Self::Update(update) => update.wire_len(),
+            Self::Notification(notification) => notification.wire_len(),
+            Self::KeepAlive => 0,
+            Self::Unknown { data, .. } => data.len()
+        }
+    }
+
+    /// This function serializes the message like [BGPMessage::pack], but fails instead of truncating a length, which doesn't fit into its
+    /// length field. The whole message must not be longer than [MAX_MESSAGE_LENGTH] bytes.
+    pub fn try_pack(&self) -> Result<Vec<u8>, PackError> {
+        match self {
+            Self::Open(open) => {
+                open.optional_parameters.iter().try_for_each(OptionalParameter::check_lengths)?;
+                if !cfg!(feature = "rfc9072") {
+                    check_length("Optional parameters", open.wire_len() - 10, u8::MAX as usize)?;
+                }
+            },
+            Self::Update(update) => update.path_attributes.iter().try_for_each(|attribute| attribute.try_pack().map(|_| ()))?,
+            _ => {}
+        }
+
+        let bytes = self.pack();
+        check_length("Message", bytes.len(), MAX_MESSAGE_LENGTH)?;
+        Ok(bytes)
+    }
+
+    /// This function serializes the message like [BGPMessage::pack], but appends the bytes to the specified buffer. This allows the
+    /// reuse of a single buffer when sending many messages.
+    pub fn pack_into(&self, buffer: &mut Vec<u8>) {
+        self.pack_into_with(buffer, &SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the message like [BGPMessage::pack_with], but appends the bytes to the specified buffer.
+    pub fn pack_into_with(&self, buffer: &mut Vec<u8>, context: &SessionContext) {
+        let start = buffer.len();
+        buffer.extend_from_slice(&BGPMessageHeader::STANDARD_MARKER);
+        buffer.extend_from_slice(&[0, 0, self.kind()]);
+        match self {
+            Self::Open(open) => buffer.extend(open.pack()),
+            Self::Update(update) => update.pack_into_with(buffer, context),
+            Self::Notification(notification) => buffer.extend(notification.pack()),
+            Self::KeepAlive => {},
+            Self::Unknown { data, .. } => buffer.extend_from_slice(data)
+        }
+
+        // The length of the message is only known after the body was written, so it's patched into the header afterward
+        let length = (buffer.len() - start) as u16;
+        buffer[start + 16..start + 18].copy_from_slice(&length.to_be_bytes());
+    }
+
+    /// This function returns the numeric message type of this message as sent in the BGP header.
+    pub fn kind(&self) -> u8 {
+        match self {
+            Self::Open(_) => 1,
+            Self::Update(_) => 2,
+            Self::Notification(_) => 3,
+            Self::KeepAlive => 4,
+            Self::Unknown { kind, .. } => *kind
+        }
+    }
+
+    /// This function returns the human-readable name of this message's type, like used in the RFCs. This is used for logging and metrics.
+    pub fn type_name(&self) -> &'static str {
+        match self.kind() {
+            1 => "OPEN",
+            2 => "UPDATE",
+            3 => "NOTIFICATION",
+            4 => "KEEPALIVE",
+            5 => "ROUTE-REFRESH",
+            _ => "UNKNOWN"
+        }
+    }
+
+    /// This function renders the message as compact JSON object for machine-readable logs without depending on serde. Addresses, prefixes
+    /// and path attributes are rendered as strings in their [Display] form and raw data is rendered as hex string.
+    pub fn to_json(&self) -> String {
+        let fields = match self {
+            Self::Open(open) => format!(
+                r#","version":{},"autonomous_system":{},"hold_time":{},"bgp_identifier":{},"capabilities":{}"#,
+                open.version,
+                open.autonomous_system,
+                open.hold_time,
+                json_string(&open.bgp_identifier_ip().to_string()),
+                json_array(&open.capabilities().collect::<Vec<_>>())
+            ),
+            Self::Update(update) => format!(",{}", update.json_fields()),
+            Self::Notification(notification) => format!(
+                r#","error_code":{},"error_subcode":{},"data":{}"#,
+                notification.error_code,
+                notification.error_subcode,
+                json_string(&hex::encode(&notification.data))
+            ),
+            Self::KeepAlive => String::new(),
+            Self::Unknown { kind, data } => format!(r#","kind":{},"data":{}"#, kind, json_string(&hex::encode(data)))
+        };
+        format!(r#"{{"type":{}{}}}"#, json_string(self.type_name()), fields)
+    }
+}
+
+/// This struct describes where and why the decoding of a message failed. It contains the offset of the failure relative to the start of
+/// the message, the kind of the failing parser and a hex dump of the bytes around the offset.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodeDiagnostic {
+    pub offset: usize,
+    pub kind: ErrorKind,
+    pub snippet: String
+}
+
+impl DecodeDiagnostic {
+    /// The amount of bytes shown before and after the offset of the failure in the hex dump
+    const SNIPPET_CONTEXT: usize = 8;
+
+    fn new(input: &[u8], offset: usize, kind: ErrorKind) -> Self {
+        let start = offset.saturating_sub(Self::SNIPPET_CONTEXT);
+        let end = min(offset + Self::SNIPPET_CONTEXT, input.len());
+        let bytes: Vec<String> = (start..end)
+            .map(|index| if index == offset { format!("[{:02x}]", input[index]) } else { format!("{:02x}", input[index]) })
+            .collect();
+        Self { offset, kind, snippet: bytes.join(" ") }
+    }
+}
+
+impl Display for DecodeDiagnostic {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Failed at byte {} ({}): {}", self.offset, self.kind.description(), self.snippet)
+    }
+}
+
+impl std::error::Error for DecodeDiagnostic {}
+
+/// This enum represents the errors returned when parsing an element with [BGPElement::parse]. The offsets are relative to the start of the
+/// parsed input.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum BgpParseError {
+    /// The input ended before the element was completely read
+    Truncated { offset: usize },
+
+    /// The input contains a value not allowed at this position, like an invalid length or type code
+    BadTag { offset: usize },
+
+    /// The input contains a message with a type not supported by this implementation
+    UnsupportedMessageType { offset: usize, kind: u8 },
+
+    /// The input contains an update message with lengths inconsistent with the message, the offset is the start of the update message's
+    /// body. The error can be converted into the NOTIFICATION sent to the peer.
+    MalformedUpdate { offset: usize, error: UpdateError }
+}
+
+impl Display for BgpParseError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated { offset } => write!(formatter, "Input truncated at byte {}", offset),
+            Self::BadTag { offset } => write!(formatter, "Invalid value at byte {}", offset),
+            Self::UnsupportedMessageType { offset, kind } => write!(formatter, "Unsupported message type {} at byte {}", kind, offset),
+            Self::MalformedUpdate { offset, error } => write!(formatter, "Malformed update message at byte {} => {}", offset, error)
+        }
+    }
+}
+
+impl std::error::Error for BgpParseError {}
+
+/// This enum represents the errors returned when packing an element with [BGPMessage::try_pack] or [PathAttribute::try_pack] and when
+/// building update messages with [build_updates]. The infallible pack functions truncate such lengths silently, so the fallible functions
+/// should be used for elements built from user input.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum PackError {
+    /// The element's data doesn't fit into the length field of the element
+    TooLong { element: &'static str, length: usize, maximum: usize },
+
+    /// The element can't be packed without the path attribute with the type code, like IPv6 routes without the MP_REACH_NLRI attribute
+    MissingAttribute { element: &'static str, kind: u8 }
+}
+
+impl Display for PackError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLong { element, length, maximum } => {
+                write!(formatter, "{} is {} bytes long, but only {} bytes are allowed", element, length, maximum)
+            },
+            Self::MissingAttribute { element, kind } => write!(formatter, "{} requires the path attribute with type code {}", element, kind)
+        }
+    }
+}
+
+impl std::error::Error for PackError {}
+
+/// This function returns an error, if the length of the element exceeds the maximum length encodable in the element's length field.
+pub(crate) fn check_length(element: &'static str, length: usize, maximum: usize) -> Result<(), PackError> {
+    if length > maximum {
+        return Err(PackError::TooLong { element, length, maximum });
+    }
+    Ok(())
+}
+
+/// This trait is implemented by the elements of the BGP that can be parsed from the bytes sent over the wire. Unlike the nom-based unpack
+/// functions used internally, errors are returned as [BgpParseError], so users of this crate aren't coupled to the version of nom.
+pub trait BGPElement: Sized {
+    /// This function parses the element from the start of the input and returns the element with the remaining input.
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError>;
+}
+
+impl BGPElement for BGPMessage {
+    /// This function parses a message like [BGPMessage::unpack], but messages with a type unknown to this implementation are rejected.
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        Self::parse_with(input, &SessionContext::four_octet_asn())
+    }
+}
+
+impl BGPElement for BGPMessageHeader {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::peek)
+    }
+}
+
+impl BGPElement for OpenMessage {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::unpack)
+    }
+}
+
+impl BGPElement for UpdateMessage {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        Self::parse_with(input, &SessionContext::four_octet_asn())
+    }
+}
+
+/// This trait is implemented by the elements of the BGP, whose encoding depends on the capabilities negotiated in the session. Like
+/// [BGPElement], errors are returned as [BgpParseError].
+pub trait ParameterizedBGPElement: Sized {
+    /// This function parses the element from the start of the input with the encoding determined by the session context and returns the
+    /// element with the remaining input.
+    fn parse_with<'a>(input: &'a [u8], context: &SessionContext) -> Result<(Self, &'a [u8]), BgpParseError>;
+}
+
+impl ParameterizedBGPElement for BGPMessage {
+    /// This function parses a message like [BGPMessage::unpack_with], but messages with a type unknown to this implementation are rejected.
+    fn parse_with<'a>(input: &'a [u8], context: &SessionContext) -> Result<(Self, &'a [u8]), BgpParseError> {
+        Self::validate_update(input).map_err(|error| BgpParseError::MalformedUpdate { offset: BGPMessageHeader::LENGTH, error })?;
+        match parse_element(input, |input| Self::unpack_with(input, context))? {
+            (Self::Unknown { kind, .. }, _) => Err(BgpParseError::UnsupportedMessageType { offset: BGPMessageHeader::LENGTH - 1, kind }),
+            result => Ok(result)
+        }
+    }
+}
+
+impl ParameterizedBGPElement for UpdateMessage {
+    fn parse_with<'a>(input: &'a [u8], context: &SessionContext) -> Result<(Self, &'a [u8]), BgpParseError> {
+        Self::validate_length_fields(input).map_err(|error| BgpParseError::MalformedUpdate { offset: 0, error })?;
+        parse_element(input, |input| Self::unpack_with(input, context))
+    }
+}
+
+impl BGPElement for NotificationMessage {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::unpack)
+    }
+}
+
+impl BGPElement for PathAttribute {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::unpack)
+    }
+}
+
+/// This enum represents the errors returned when creating an open message with invalid values.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum OpenError {
+    /// The hold time is one or two seconds, which isn't allowed following to RFC 4271
+    UnacceptableHoldTime(u16),
+
+    /// The BGP identifier is no valid unicast IPv4 address
+    BadBgpIdentifier(Ipv4Addr)
+}
+
+impl Display for OpenError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnacceptableHoldTime(hold_time) => write!(formatter, "Unacceptable hold time of {} seconds", hold_time),
+            Self::BadBgpIdentifier(identifier) => write!(formatter, "BGP identifier {} is no valid unicast address", identifier)
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+/// This function returns the hold time of a session, which is the smaller hold time of the local and the remote open message. If one of
+/// the routers sent a hold time of zero, the hold time is zero and no keepalive messages are sent in the session.
+///
+/// ## References
+/// - [OPEN Message Format, Section 4.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.2)
+pub fn negotiated_hold_time(local: u16, remote: u16) -> u16 {
+    min(local, remote)
+}
+
+/// This struct contains the policy for the hold times received from peers. Peers advertising a hold time below the minimum hold time are
+/// rejected, so a misconfigured peer can't force the local router to send keepalive messages at a high rate.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub struct HoldTimePolicy {
+    /// The minimum hold time accepted from peers in seconds. Hold times of zero are always accepted, as they disable the keepalives.
+    pub minimum_hold_time: u16
+}
+
+impl Default for HoldTimePolicy {
+    fn default() -> Self {
+        Self { minimum_hold_time: 3 }
+    }
+}
+
+impl HoldTimePolicy {
+    /// This function returns the hold time of the session like [negotiated_hold_time]. If the remote hold time is one or two seconds or
+    /// below the minimum hold time, this function returns the Unacceptable Hold Time NOTIFICATION to be sent to the peer.
+    ///
+    /// ## References
+    /// - [OPEN Message Error Handling, Section 6.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.2)
+    pub fn negotiate(&self, local: u16, remote: u16) -> Result<u16, NotificationMessage> {
+        if remote != 0 && remote < self.minimum_hold_time.max(3) {
+            let notification = NotificationMessage::open_error(OpenErrorSubcode::UnacceptableHoldTime);
+            return Err(NotificationMessage { data: remote.to_be_bytes().to_vec(), ..notification });
+        }
+        Ok(negotiated_hold_time(local, remote))
+    }
 }
 
 /// This struct is the type-safe implementation for handling the incoming/outgoing open message. The open message is the BGP equivalent of
-/// a handshake between two pair routers.
+/// a handshake between two pair routers. The fields are public for the parsing of received messages, but [OpenMessage::new] should be
+/// preferred when creating messages to be sent.
 ///
 /// ## Reference
 /// - [OPEN Message Format, Section 4.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.2)
@@ -135,16 +709,185 @@ pub struct OpenMessage {
 }
 
 impl OpenMessage {
+    /// This function creates an open message for BGP version 4 after validating the specified values. The hold time must be zero or at
+    /// least three seconds and the BGP identifier must be a valid unicast IPv4 address.
+    ///
+    /// ## References
+    /// - [OPEN Message Format, Section 4.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.2)
+    pub fn new(autonomous_system: u16, hold_time: u16, bgp_identifier: Ipv4Addr, optional_parameters: Vec<OptionalParameter>)
+        -> Result<Self, OpenError> {
+        if matches!(hold_time, 1 | 2) {
+            return Err(OpenError::UnacceptableHoldTime(hold_time));
+        }
+
+        if bgp_identifier.is_unspecified() || bgp_identifier.is_multicast() || bgp_identifier.is_broadcast() {
+            return Err(OpenError::BadBgpIdentifier(bgp_identifier));
+        }
+
+        let mut message = Self { version: 4, autonomous_system, hold_time, bgp_identifier: bgp_identifier.to_bits(), optional_parameters };
+        message.normalize_capabilities();
+        Ok(message)
+    }
+
+    /// This function merges all capabilities optional parameters into a single parameter at the position of the first one and removes
+    /// capabilities indicating a feature already indicated by a previous capability (like a second multiprotocol extensions capability
+    /// for the same address family), as some peers reject such open messages. This is called by [OpenMessage::new].
+    pub fn normalize_capabilities(&mut self) {
+        let mut capabilities: Vec<Capability> = Vec::new();
+        for capability in self.capabilities() {
+            if !capabilities.iter().any(|other_capability| other_capability.matches(capability)) {
+                capabilities.push(capability.clone());
+            }
+        }
+
+        let is_capabilities = |parameter: &OptionalParameter| matches!(parameter, OptionalParameter::Capabilities(_));
+        let Some(index) = self.optional_parameters.iter().position(is_capabilities) else {
+            return;
+        };
+        self.optional_parameters.retain(|parameter| !is_capabilities(parameter));
+        self.optional_parameters.insert(index, OptionalParameter::Capabilities(capabilities));
+    }
+
+    /// This function returns the BGP identifier of this open message as IPv4 address, like it's written in configurations and logs.
+    pub fn bgp_identifier_ip(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.bgp_identifier)
+    }
+
+    /// This function returns an iterator over the capabilities of all capabilities optional parameters in this open message.
+    pub fn capabilities(&self) -> impl Iterator<Item = &Capability> {
+        self.optional_parameters.iter()
+            .filter_map(|parameter| match parameter {
+                OptionalParameter::Capabilities(capabilities) => Some(capabilities),
+                _ => None
+            })
+            .flatten()
+    }
+
     fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, (mut message, optional_parameters_bytes, extended)) = Self::unpack_fixed(input)?;
+        let parser = |bytes| OptionalParameter::unpack(bytes, extended);
+        message.optional_parameters = many_bounded(MAX_OPTIONAL_PARAMETERS, parser)(optional_parameters_bytes)?.1;
+        Ok((input, message))
+    }
+
+    /// This function parses the body of an open message (without the BGP header) like the strict parser, but skips optional parameters
+    /// which can't be parsed instead of rejecting the whole message. The errors of the skipped parameters are returned with the message,
+    /// so they can be logged or reported to the peer.
+    pub fn unpack_lenient(input: &[u8]) -> IResult<&[u8], (Self, Vec<ParamParseError>)> {
+        let (input, (mut message, optional_parameters_bytes, extended)) = Self::unpack_fixed(input)?;
+        let (optional_parameters, errors) = params::unpack_lenient(optional_parameters_bytes, extended);
+        message.optional_parameters = optional_parameters;
+        Ok((input, (message, errors)))
+    }
+
+    /// This function parses the fixed fields of the open message and returns the message without optional parameters, the bytes of the
+    /// optional parameters and whether they're encoded with the extended length. Following to RFC 9072, the extended encoding is used when
+    /// the type of the first parameter is 255, which is reserved for this purpose. This is only recognized with the `rfc9072` feature.
+    ///
+    /// ## References
+    /// - [Extended Optional Parameters Length, Section 2 RFC 9072](https://datatracker.ietf.org/doc/html/rfc9072#section-2)
+    fn unpack_fixed(input: &[u8]) -> IResult<&[u8], (Self, &[u8], bool)> {
         let (input, version) = be_u8(input)?;
         let (input, autonomous_system) = be_u16(input)?;
         let (input, hold_time) = be_u16(input)?;
         let (input, bgp_identifier) = be_u32(input)?;
 
         let (input, optional_parameters_length) = be_u8(input)?;
+        let extended = cfg!(feature = "rfc9072") && optional_parameters_length != 0 && input.first() == Some(&u8::MAX);
+        let (input, optional_parameters_length) = if extended {
+            let (input, _) = be_u8(input)?;
+            be_u16(input)?
+        } else {
+            (input, optional_parameters_length as u16)
+        };
+
         let (input, optional_parameters_bytes) = take(optional_parameters_length as usize)(input)?;
-        let (_, optional_parameters) = many0(OptionalParameter::unpack).parse(optional_parameters_bytes)?;
-        Ok((input, Self { version, autonomous_system, hold_time, bgp_identifier, optional_parameters }))
+        let message = Self { version, autonomous_system, hold_time, bgp_identifier, optional_parameters: Vec::new() };
+        Ok((input, (message, optional_parameters_bytes, extended)))
+    }
+
+    /// This function returns the length of the open message's body without the BGP header like serialized by [OpenMessage::pack].
+    pub fn wire_len(&self) -> usize {
+        let optional_parameters_length: usize = self.optional_parameters.iter().map(|parameter| parameter.wire_len(false)).sum();
+        if cfg!(feature = "rfc9072") && optional_parameters_length > u8::MAX as usize {
+            13 + self.optional_parameters.iter().map(|parameter| parameter.wire_len(true)).sum::<usize>()
+        } else {
+            10 + optional_parameters_length
+        }
+    }
+
+    /// This function serializes the body of the open message. If the optional parameters don't fit into the one-byte length, they're
+    /// encoded with the extended length of RFC 9072 instead when the `rfc9072` feature is enabled.
+    ///
+    /// ## References
+    /// - [Extended Optional Parameters Length, Section 2 RFC 9072](https://datatracker.ietf.org/doc/html/rfc9072#section-2)
+    fn pack(&self) -> Vec<u8> {
+        let mut optional_parameters: Vec<u8> = self.optional_parameters.iter().flat_map(|parameter| parameter.pack(false)).collect();
+        let extended = cfg!(feature = "rfc9072") && optional_parameters.len() > u8::MAX as usize;
+        if extended {
+            optional_parameters = self.optional_parameters.iter().flat_map(|parameter| parameter.pack(true)).collect();
+        }
+
+        let mut bytes = vec![self.version];
+        bytes.extend_from_slice(&self.autonomous_system.to_be_bytes());
+        bytes.extend_from_slice(&self.hold_time.to_be_bytes());
+        bytes.extend_from_slice(&self.bgp_identifier.to_be_bytes());
+        if extended {
+            bytes.extend_from_slice(&[u8::MAX, u8::MAX]);
+            bytes.extend_from_slice(&(optional_parameters.len() as u16).to_be_bytes());
+        } else {
+            bytes.push(optional_parameters.len() as u8);
+        }
+        bytes.extend(optional_parameters);
+        bytes
+    }
+}
+
+/// This struct represents the BGP identifier (router ID) of a BGP speaker, which is an IPv4 address unique in the network of the speaker.
+///
+/// ## References
+/// - [OPEN Message Format, Section 4.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub struct BgpIdentifier(pub Ipv4Addr);
+
+impl BgpIdentifier {
+    /// The BGP identifier used when no identifier can be derived from the local interfaces. The address is taken from the documentation
+    /// network `192.0.2.0/24` (RFC 5737), so it's recognizable as placeholder to be replaced by the operator.
+    pub const PLACEHOLDER: BgpIdentifier = BgpIdentifier(Ipv4Addr::new(192, 0, 2, 1));
+
+    /// This function derives a stable BGP identifier from the IPv4 addresses assigned to the local interfaces, like routers pick their
+    /// router ID when none is configured. The highest address (excluding the loopback network `127.0.0.0/8`) is used. The addresses are
+    /// read from the routing tables of the Linux kernel, so this function fails on other operating systems.
+    pub fn from_interface() -> anyhow::Result<Self> {
+        let fib_trie = std::fs::read_to_string("/proc/net/fib_trie")
+            .map_err(|error| anyhow::anyhow!("Unable to read local addresses => {}", error))?;
+        Self::local_addresses(&fib_trie).into_iter()
+            .filter(|address| !address.is_loopback() && !address.is_unspecified() && !address.is_multicast() && !address.is_broadcast())
+            .max()
+            .map(Self)
+            .ok_or(anyhow::anyhow!("No IPv4 address assigned to any interface"))
+    }
+
+    /// This function returns the local addresses listed in the FIB trie of the kernel. Every local address is followed by a line with the
+    /// `/32 host LOCAL` route.
+    fn local_addresses(fib_trie: &str) -> Vec<Ipv4Addr> {
+        let lines: Vec<&str> = fib_trie.lines().map(str::trim).collect();
+        lines.windows(2)
+            .filter(|lines| lines[1] == "/32 host LOCAL")
+            .filter_map(|lines| lines[0].strip_prefix("|-- ").and_then(|address| address.parse().ok()))
+            .collect()
+    }
+}
+
+impl From<BgpIdentifier> for Ipv4Addr {
+    fn from(identifier: BgpIdentifier) -> Self {
+        identifier.0
+    }
+}
+
+impl Display for BgpIdentifier {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.0)
     }
 }
 
@@ -184,18 +927,39 @@ impl Display for PathAttributeFlags {
 /// ## References
 /// - [UPDATE Message Format, Section 4.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.3)
 /// - [Path Attributes, Section 5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-5)
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 pub enum PathAttribute {
     Origin(Origin),
+    ASPath(Vec<ASPathSegment>),
+    NextHop(Ipv4Addr),
+    MultiExitDisc(u32),
+    LocalPref(u32),
+    OriginatorId(Ipv4Addr),
+    ClusterList(Vec<Ipv4Addr>),
     MpReachableNLRI(MultiprotocolReachablePathAttribute),
     MpUnreachableNLRI(MultiprotocolUnreachablePathAttribute),
     Communities(Vec<Community>),
     ExtendedCommunities(Vec<Community>),
+    #[cfg(feature = "rfc9012")]
+    TunnelEncapsulation(Vec<TunnelTlv>),
+
+    /// This value indicates the P-Multicast Service Interface (PMSI) Tunnel attribute, which identifies the provider tunnel used for the
+    /// multicast traffic of MVPN and EVPN routes. The label is kept as the raw 3-byte field (it carries the VNI for VXLAN tunnels) and the
+    /// tunnel identifier, whose format depends on the tunnel type, is kept as opaque bytes.
+    ///
+    /// ## References
+    /// - [PMSI Tunnel Attribute, Section 5 RFC 6514](https://datatracker.ietf.org/doc/html/rfc6514#section-5)
+    #[cfg(feature = "rfc6514")]
+    PmsiTunnel { flags: u8, tunnel_type: u8, label: u32, tunnel_id: Vec<u8> },
     Unknown { flags: PathAttributeFlags, kind: u8, data: Vec<u8> }
 }
 
 impl PathAttribute {
     fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        Self::unpack_with(input, &SessionContext::four_octet_asn())
+    }
+
+    fn unpack_with<'a>(input: &'a [u8], context: &SessionContext) -> IResult<&'a [u8], Self> {
         let (input, flags) = be_u8(input)?;
         let flags = PathAttributeFlags::from_bits(flags).ok_or(nom::Err::Error(Error::new(input, ErrorKind::Tag)))?;
         let (input, kind) = be_u8(input)?;
@@ -209,11 +973,31 @@ impl PathAttribute {
 
         let (input, data) = take(length)(input)?;
         Ok((input, match kind {
-            0x01 => Self::Origin(Origin::from(be_u8(data)?.1)),
+            // The attributes with fixed-size values must be consumed completely, so attributes with invalid lengths aren't re-packed with
+            // another length
+            0x01 => Self::Origin(Origin::from(all_consuming(be_u8).parse(data)?.1)),
+            0x02 => {
+                let parser = |segment| ASPathSegment::unpack_with(segment, context.four_octet_asn);
+                Self::ASPath(all_consuming(many_bounded(MAX_AS_PATH_SEGMENTS, parser)).parse(data)?.1)
+            },
+            0x03 => Self::NextHop(Ipv4Addr::from_bits(all_consuming(be_u32).parse(data)?.1)),
+            0x04 => Self::MultiExitDisc(all_consuming(be_u32).parse(data)?.1),
+            0x05 => Self::LocalPref(all_consuming(be_u32).parse(data)?.1),
+            0x09 => Self::OriginatorId(Ipv4Addr::from_bits(all_consuming(be_u32).parse(data)?.1)),
+            0x0A => Self::ClusterList(all_consuming(many0(be_u32)).parse(data)?.1.into_iter().map(Ipv4Addr::from_bits).collect()),
             0x08 => Self::Communities(many1(|value| Community::unpack(value, false)).parse(data)?.1),
             0x0E => Self::MpReachableNLRI(MultiprotocolReachablePathAttribute::unpack(data)?.1),
             0x0F => Self::MpUnreachableNLRI(MultiprotocolUnreachablePathAttribute::unpack(data)?.1),
             0x10 => Self::ExtendedCommunities(many1(|value| Community::unpack(value, true)).parse(data)?.1),
+            #[cfg(feature = "rfc9012")]
+            0x17 => Self::TunnelEncapsulation(all_consuming(many0(TunnelTlv::unpack)).parse(data)?.1),
+            #[cfg(feature = "rfc6514")]
+            0x16 => {
+                let (data, flags) = be_u8(data)?;
+                let (data, tunnel_type) = be_u8(data)?;
+                let (tunnel_id, label) = be_u24(data)?;
+                Self::PmsiTunnel { flags, tunnel_type, label, tunnel_id: tunnel_id.to_vec() }
+            },
             _ => Self::Unknown {
                 flags,
                 kind,
@@ -221,25 +1005,229 @@ impl PathAttribute {
             }
         }))
     }
+
+    /// This function serializes the path attribute with its flags, type code and length. The extended length flag is set automatically if
+    /// the attribute's data doesn't fit into the one-byte length. Unknown attributes received with the extended length flag keep it, so
+    /// attributes not interpreted by this implementation are passed on byte-identically.
+    pub fn pack(&self) -> Vec<u8> {
+        self.pack_with(&SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the path attribute like [PathAttribute::pack], but with the encoding determined by the capabilities
+    /// negotiated in the session. This is the counterpart of [PathAttribute::unpack_with], so the AS_PATH is sent with two-octet AS numbers
+    /// to peers without support for four-octet AS numbers.
+    pub fn pack_with(&self, context: &SessionContext) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.pack_into_with(&mut bytes, context);
+        bytes
+    }
+
+    /// This function returns the length of the serialized path attribute including the flags, type code and length like serialized by
+    /// [PathAttribute::pack].
+    pub fn wire_len(&self) -> usize {
+        self.wire_len_with(&SessionContext::four_octet_asn())
+    }
+
+    /// This function returns the length of the serialized path attribute like serialized by [PathAttribute::pack_with].
+    pub fn wire_len_with(&self, context: &SessionContext) -> usize {
+        let length = match self {
+            Self::Origin(_) => 1,
+            Self::ASPath(segments) => segments.iter().map(|segment| segment.wire_len_with(context.four_octet_asn)).sum(),
+            Self::NextHop(_) | Self::OriginatorId(_) | Self::MultiExitDisc(_) | Self::LocalPref(_) => 4,
+            Self::ClusterList(cluster_ids) => cluster_ids.len() * 4,
+            Self::Communities(communities) | Self::ExtendedCommunities(communities) => communities.iter().map(Community::wire_len).sum(),
+            Self::MpReachableNLRI(reachable) => reachable.wire_len(),
+            Self::MpUnreachableNLRI(unreachable) => unreachable.wire_len(),
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(tunnels) => tunnels.iter().map(TunnelTlv::wire_len).sum(),
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { tunnel_id, .. } => 5 + tunnel_id.len(),
+            Self::Unknown { data, .. } => data.len()
+        };
+
+        if length > u8::MAX as usize || self.flags().contains(PathAttributeFlags::EXTENDED_LENGTH) { 4 + length } else { 3 + length }
+    }
+
+    /// This function serializes the path attribute like [PathAttribute::pack], but fails instead of truncating a length, which doesn't fit
+    /// into its length field.
+    pub fn try_pack(&self) -> Result<Vec<u8>, PackError> {
+        match self {
+            Self::ASPath(segments) => segments.iter().try_for_each(ASPathSegment::check_lengths)?,
+            Self::MpReachableNLRI(reachable) => {
+                reachable.evpn_network_layer_reachability_information.iter().try_for_each(EvpnRoute::check_lengths)?
+            },
+            Self::MpUnreachableNLRI(unreachable) => {
+                unreachable.evpn_network_layer_reachability_information.iter().try_for_each(EvpnRoute::check_lengths)?
+            },
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(tunnels) => tunnels.iter().try_for_each(TunnelTlv::check_lengths)?,
+            _ => {}
+        }
+
+        let bytes = self.pack();
+        let header_length = if bytes[0] & PathAttributeFlags::EXTENDED_LENGTH.bits() != 0 { 4 } else { 3 };
+        check_length("Path attribute", bytes.len() - header_length, u16::MAX as usize)?;
+        Ok(bytes)
+    }
+
+    /// This function serializes the path attribute like [PathAttribute::pack], but appends the bytes to the specified buffer.
+    pub fn pack_into(&self, buffer: &mut Vec<u8>) {
+        self.pack_into_with(buffer, &SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the path attribute like [PathAttribute::pack_with], but appends the bytes to the specified buffer.
+    pub fn pack_into_with(&self, buffer: &mut Vec<u8>, context: &SessionContext) {
+        let start = buffer.len();
+        let flags = self.flags().difference(PathAttributeFlags::EXTENDED_LENGTH);
+        buffer.extend_from_slice(&[(flags | PathAttributeFlags::EXTENDED_LENGTH).bits(), self.kind(), 0, 0]);
+        match self {
+            Self::Origin(origin) => buffer.push(*origin as u8),
+            Self::ASPath(segments) => segments.iter().for_each(|segment| buffer.extend(segment.pack_with(context.four_octet_asn))),
+            Self::NextHop(address) | Self::OriginatorId(address) => buffer.extend_from_slice(&address.octets()),
+            Self::ClusterList(cluster_ids) => cluster_ids.iter().for_each(|cluster_id| buffer.extend_from_slice(&cluster_id.octets())),
+            Self::MultiExitDisc(value) | Self::LocalPref(value) => buffer.extend_from_slice(&value.to_be_bytes()),
+            Self::Communities(communities) | Self::ExtendedCommunities(communities) => {
+                communities.iter().for_each(|community| buffer.extend(community.pack()))
+            },
+            Self::MpReachableNLRI(reachable) => buffer.extend(reachable.pack()),
+            Self::MpUnreachableNLRI(unreachable) => buffer.extend(unreachable.pack()),
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(tunnels) => tunnels.iter().for_each(|tunnel| buffer.extend(tunnel.pack())),
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { flags, tunnel_type, label, tunnel_id } => {
+                buffer.extend_from_slice(&[*flags, *tunnel_type]);
+                buffer.extend_from_slice(&label.to_be_bytes()[1..]);
+                buffer.extend_from_slice(tunnel_id);
+            },
+            Self::Unknown { data, .. } => buffer.extend_from_slice(data)
+        }
+
+        // The attribute is written with the extended length first. If the data fits into a single byte, the second length byte is removed
+        // and the extended length flag is cleared.
+        let length = buffer.len() - start - 4;
+        if length > u8::MAX as usize || self.flags().contains(PathAttributeFlags::EXTENDED_LENGTH) {
+            buffer[start + 2..start + 4].copy_from_slice(&(length as u16).to_be_bytes());
+        } else {
+            buffer[start] = flags.bits();
+            buffer[start + 2] = length as u8;
+            buffer.remove(start + 3);
+        }
+    }
+
+    /// This function returns a copy of the path attribute with the communities sorted, so attributes only differing in the order of
+    /// their communities compare equal.
+    fn normalized(&self) -> Self {
+        let mut attribute = self.clone();
+        if let Self::Communities(communities) | Self::ExtendedCommunities(communities) = &mut attribute {
+            communities.sort();
+        }
+        attribute
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Self::Origin(_) => 0x01,
+            Self::ASPath(_) => 0x02,
+            Self::NextHop(_) => 0x03,
+            Self::MultiExitDisc(_) => 0x04,
+            Self::LocalPref(_) => 0x05,
+            Self::OriginatorId(_) => 0x09,
+            Self::ClusterList(_) => 0x0A,
+            Self::Communities(_) => 0x08,
+            Self::MpReachableNLRI(_) => 0x0E,
+            Self::MpUnreachableNLRI(_) => 0x0F,
+            Self::ExtendedCommunities(_) => 0x10,
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(_) => 0x17,
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { .. } => 0x16,
+            Self::Unknown { kind, .. } => *kind
+        }
+    }
+
+    fn flags(&self) -> PathAttributeFlags {
+        match self {
+            Self::Origin(_) | Self::ASPath(_) | Self::NextHop(_) | Self::LocalPref(_) => PathAttributeFlags::TRANSITIVE,
+            Self::MultiExitDisc(_) | Self::OriginatorId(_) | Self::ClusterList(_) | Self::MpReachableNLRI(_) | Self::MpUnreachableNLRI(_) => {
+                PathAttributeFlags::OPTIONAL
+            },
+            Self::Communities(_) | Self::ExtendedCommunities(_) => PathAttributeFlags::OPTIONAL | PathAttributeFlags::TRANSITIVE,
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(_) => PathAttributeFlags::OPTIONAL | PathAttributeFlags::TRANSITIVE,
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { .. } => PathAttributeFlags::OPTIONAL | PathAttributeFlags::TRANSITIVE,
+            Self::Unknown { flags, .. } => *flags
+        }
+    }
+}
+
+impl Debug for PathAttribute {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Origin(origin) => formatter.debug_tuple("Origin").field(origin).finish(),
+            Self::ASPath(segments) => formatter.debug_tuple("ASPath").field(segments).finish(),
+            Self::NextHop(address) => formatter.debug_tuple("NextHop").field(address).finish(),
+            Self::MultiExitDisc(value) => formatter.debug_tuple("MultiExitDisc").field(value).finish(),
+            Self::LocalPref(value) => formatter.debug_tuple("LocalPref").field(value).finish(),
+            Self::OriginatorId(address) => formatter.debug_tuple("OriginatorId").field(address).finish(),
+            Self::ClusterList(cluster_ids) => formatter.debug_tuple("ClusterList").field(cluster_ids).finish(),
+            Self::MpReachableNLRI(reachable) => formatter.debug_tuple("MpReachableNLRI").field(reachable).finish(),
+            Self::MpUnreachableNLRI(unreachable) => formatter.debug_tuple("MpUnreachableNLRI").field(unreachable).finish(),
+            Self::Communities(communities) => formatter.debug_tuple("Communities").field(communities).finish(),
+            Self::ExtendedCommunities(communities) => formatter.debug_tuple("ExtendedCommunities").field(communities).finish(),
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(tunnels) => formatter.debug_tuple("TunnelEncapsulation").field(tunnels).finish(),
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { flags, tunnel_type, label, tunnel_id } => formatter.debug_struct("PmsiTunnel")
+                .field("flags", flags)
+                .field("tunnel_type", tunnel_type)
+                .field("label", label)
+                .field("tunnel_id", &HexDump(tunnel_id))
+                .finish(),
+            Self::Unknown { flags, kind, data } => formatter.debug_struct("Unknown")
+                .field("flags", flags)
+                .field("kind", kind)
+                .field("data", &HexDump(data))
+                .finish()
+        }
+    }
 }
 
 impl Display for PathAttribute {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Origin(origin) => write!(formatter, "{:?}", origin),
+            Self::ASPath(segments) => {
+                let segments: Vec<String> = segments.iter().map(ASPathSegment::to_string).collect();
+                write!(formatter, "AS path {}", segments.join(" "))
+            },
+            Self::NextHop(address) => write!(formatter, "Next hop {}", address),
+            Self::MultiExitDisc(value) => write!(formatter, "Multi exit discriminator {}", value),
+            Self::LocalPref(value) => write!(formatter, "Local preference {}", value),
+            Self::OriginatorId(address) => write!(formatter, "Originator ID {}", address),
+            Self::ClusterList(cluster_ids) => {
+                let cluster_ids: Vec<String> = cluster_ids.iter().map(Ipv4Addr::to_string).collect();
+                write!(formatter, "Cluster list {}", cluster_ids.join(" "))
+            },
             Self::Communities(communities) => write!(formatter, "{} communities", communities.len()),
             Self::ExtendedCommunities(communities) => write!(formatter, "{} extended communities", communities.len()),
+            #[cfg(feature = "rfc9012")]
+            Self::TunnelEncapsulation(tunnels) => write!(formatter, "{} tunnel encapsulations", tunnels.len()),
+            #[cfg(feature = "rfc6514")]
+            Self::PmsiTunnel { tunnel_type, label, tunnel_id, .. } => {
+                write!(formatter, "PMSI tunnel {} (Type: {}, Label: {})", hex::encode(tunnel_id), tunnel_type, label)
+            },
             Self::MpUnreachableNLRI(reachable) => write!(
                 formatter,
                 "{} newly unreachable {} addresses ({})",
-                reachable.network_layer_reachability_information.len(),
+                reachable.route_count(),
                 reachable.address_family,
                 reachable.subsequent_address_family
             ),
             Self::MpReachableNLRI(reachable) => write!(
                 formatter,
                 "{} newly reachable {} addresses ({})",
-                reachable.network_layer_reachability_information.len(),
+                reachable.route_count(),
                 reachable.address_family,
                 reachable.subsequent_address_family
             ),
@@ -248,6 +1236,48 @@ impl Display for PathAttribute {
     }
 }
 
+/// This enum represents the errors found when validating a received update message. The errors can be converted into the NOTIFICATION
+/// sent to the peer.
+///
+/// ## References
+/// - [UPDATE Message Error Handling, Section 6.3 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.3)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum UpdateError {
+    /// A path attribute appears more than once in the update message, the value is the type code of the duplicated attribute (Subcode 1)
+    MalformedAttributeList(u8),
+
+    /// A well-known mandatory path attribute is missing, the value is the type code of the missing attribute (Subcode 3)
+    MissingWellKnownAttribute(u8),
+
+    /// The withdrawn routes length or total path attribute length declares more bytes than available in the message (Subcode 1)
+    LengthFieldMismatch { field: &'static str, declared: usize, available: usize }
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MalformedAttributeList(kind) => write!(formatter, "Malformed attribute list (Duplicated kind: {})", kind),
+            Self::MissingWellKnownAttribute(kind) => write!(formatter, "Missing well-known attribute (Kind: {})", kind),
+            Self::LengthFieldMismatch { field, declared, available } => {
+                write!(formatter, "Length of {} exceeds the message (Declared: {}, Available: {})", field, declared, available)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl From<UpdateError> for NotificationMessage {
+    fn from(error: UpdateError) -> Self {
+        match error {
+            UpdateError::MalformedAttributeList(_) | UpdateError::LengthFieldMismatch { .. } => {
+                Self::update_error(UpdateErrorSubcode::MalformedAttributeList, Vec::new())
+            },
+            UpdateError::MissingWellKnownAttribute(kind) => Self::update_error(UpdateErrorSubcode::MissingWellKnownAttribute, vec![kind])
+        }
+    }
+}
+
 /// This struct is the type-safe implementation for handling the incoming/outgoing update message. The update messages tell the router about
 /// routes newly announced, routes withdrawn and network layer reachability information.
 ///
@@ -260,19 +1290,590 @@ pub struct UpdateMessage {
     pub network_layer_reachability_information: Vec<Prefix>
 }
 
+/// This struct contains the capabilities negotiated in a session, which change the encoding of the update messages. The default context
+/// is the context of a session without any negotiated capabilities.
+///
+/// ## References
+/// - [Protocol Extensions, Section 4 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4)
+/// - [Extended NLRI Encodings, Section 3 RFC 7911](https://datatracker.ietf.org/doc/html/rfc7911#section-3)
+/// - [BGP Extended Message Capability, Section 3 RFC 8654](https://datatracker.ietf.org/doc/html/rfc8654#section-3)
+#[derive(Clone, Debug, Eq, PartialEq, Default)]
+pub struct SessionContext {
+    /// Whether the four-octet AS number support was negotiated, so the AS numbers in the AS_PATH are encoded with four bytes
+    pub four_octet_asn: bool,
+
+    /// The address families, for which the receiving of multiple paths (ADD-PATH) was negotiated. The prefixes of these address families
+    /// are prefixed with a path identifier.
+    pub add_path: HashSet<(AddressFamily, SubsequentAddressFamily)>,
+
+    /// Whether the extended message capability was negotiated, so messages can be up to [MAX_EXTENDED_MESSAGE_LENGTH] bytes long
+    pub extended_message: bool
+}
+
+impl SessionContext {
+    /// This function returns the maximum length of the messages received in the session including the header.
+    pub fn max_message_length(&self) -> usize {
+        if self.extended_message { MAX_EXTENDED_MESSAGE_LENGTH } else { MAX_MESSAGE_LENGTH }
+    }
+
+    /// This function returns the context used by the parsers without context, which expect the four-octet AS number support to be
+    /// negotiated.
+    fn four_octet_asn() -> Self {
+        Self { four_octet_asn: true, ..Self::default() }
+    }
+}
+
 impl UpdateMessage {
-    fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+    /// This function reads the update message's body with the encoding determined by the capabilities negotiated in the session. This is
+    /// the function used to parse update messages once the session is established. The path identifiers of prefixes received with
+    /// ADD-PATH are currently skipped. The withdrawn routes and the NLRI must be consumed completely, so stray bytes not forming a complete
+    /// prefix are rejected instead of being dropped silently.
+    pub fn unpack_with<'a>(input: &'a [u8], context: &SessionContext) -> IResult<&'a [u8], Self> {
+        let add_path = context.add_path.contains(&(AddressFamily::IPv4, SubsequentAddressFamily::Unicast));
+        let unpack_prefix = move |input| {
+            let (input, _path_identifier) = take(if add_path { 4usize } else { 0 })(input)?;
+            Prefix::unpack(input, AddressFamily::IPv4)
+        };
+
         let (input, withdrawn_routes_length) = be_u16(input)?;
         let (input, withdrawn_routes) = take(withdrawn_routes_length)(input)?;
         let (input, path_attributes_length) = be_u16(input)?;
         let (nlri, path_attributes_bytes) = take(path_attributes_length)(input)?;
-        let (_, path_attributes) = many0(PathAttribute::unpack).parse(path_attributes_bytes)?;
+        let parser = |attribute| PathAttribute::unpack_with(attribute, context);
+        let (_, path_attributes) = many_bounded(MAX_PATH_ATTRIBUTES, parser)(path_attributes_bytes)?;
         Ok((&[], Self {
             path_attributes,
-            withdrawn_routes: many0(|b| Prefix::unpack(b, AddressFamily::IPv4)).parse(withdrawn_routes)?.1,
-            network_layer_reachability_information: many0(|b| Prefix::unpack(b, AddressFamily::IPv4)).parse(nlri)?.1
+            withdrawn_routes: all_consuming(many0(unpack_prefix)).parse(withdrawn_routes)?.1,
+            network_layer_reachability_information: all_consuming(many0(unpack_prefix)).parse(nlri)?.1
         }))
     }
+
+    /// This function creates the End-of-RIB marker for the specified address family, which is sent after the initial routing update to
+    /// indicate that all routes were sent. For IPv4 unicast, the marker is an update message without withdrawn routes, path attributes and
+    /// NLRI. For all other address families, the marker only contains an empty MP_UNREACH_NLRI path attribute.
+    ///
+    /// ## References
+    /// - [Marker for End-of-RIB, Section 2 RFC 4724](https://datatracker.ietf.org/doc/html/rfc4724#section-2)
+    pub fn end_of_rib(address_family: AddressFamily, subsequent_address_family: SubsequentAddressFamily) -> Self {
+        let path_attributes = match (address_family, subsequent_address_family) {
+            (AddressFamily::IPv4, SubsequentAddressFamily::Unicast) => Vec::new(),
+            _ => vec![PathAttribute::MpUnreachableNLRI(MultiprotocolUnreachablePathAttribute {
+                address_family,
+                subsequent_address_family,
+                network_layer_reachability_information: Vec::new(),
+                vpn_network_layer_reachability_information: Vec::new(),
+                evpn_network_layer_reachability_information: Vec::new()
+            })]
+        };
+        Self { withdrawn_routes: Vec::new(), path_attributes, network_layer_reachability_information: Vec::new() }
+    }
+
+    /// This function returns the address family of the End-of-RIB marker, if this update message is an End-of-RIB marker created like
+    /// [UpdateMessage::end_of_rib]. Receiving the marker for an address family completes the initial routing update of the peer, so the
+    /// stale routes retained for a graceful restart can be removed.
+    ///
+    /// ## References
+    /// - [Marker for End-of-RIB, Section 2 RFC 4724](https://datatracker.ietf.org/doc/html/rfc4724#section-2)
+    pub fn is_end_of_rib(&self) -> Option<(AddressFamily, SubsequentAddressFamily)> {
+        if !self.withdrawn_routes.is_empty() || !self.network_layer_reachability_information.is_empty() {
+            return None;
+        }
+
+        match self.path_attributes.as_slice() {
+            [] => Some((AddressFamily::IPv4, SubsequentAddressFamily::Unicast)),
+            [PathAttribute::MpUnreachableNLRI(unreachable)] if unreachable.route_count() == 0 => {
+                Some((unreachable.address_family, unreachable.subsequent_address_family))
+            },
+            _ => None
+        }
+    }
+
+    /// This function returns the prefixes announced by this update message, which are the IPv4 prefixes of the NLRI field followed by the
+    /// prefixes of the multiprotocol reachable path attributes. VPN prefixes and EVPN routes aren't included, as they aren't identified by
+    /// the prefix alone.
+    pub fn announced_prefixes(&self) -> Vec<(AddressFamily, Prefix)> {
+        let mut prefixes: Vec<(AddressFamily, Prefix)> = self.network_layer_reachability_information.iter()
+            .map(|prefix| (AddressFamily::IPv4, *prefix))
+            .collect();
+        for attribute in &self.path_attributes {
+            if let PathAttribute::MpReachableNLRI(reachable) = attribute {
+                prefixes.extend(reachable.network_layer_reachability_information.iter().map(|prefix| (reachable.address_family, *prefix)));
+            }
+        }
+        prefixes
+    }
+
+    /// This function returns the prefixes withdrawn by this update message, which are the IPv4 prefixes of the withdrawn routes field
+    /// followed by the prefixes of the multiprotocol unreachable path attributes. Like [UpdateMessage::announced_prefixes], VPN prefixes
+    /// and EVPN routes aren't included.
+    pub fn withdrawn_prefixes(&self) -> Vec<(AddressFamily, Prefix)> {
+        let mut prefixes: Vec<(AddressFamily, Prefix)> = self.withdrawn_routes.iter().map(|prefix| (AddressFamily::IPv4, *prefix)).collect();
+        for attribute in &self.path_attributes {
+            if let PathAttribute::MpUnreachableNLRI(unreachable) = attribute {
+                let address_family = unreachable.address_family;
+                prefixes.extend(unreachable.network_layer_reachability_information.iter().map(|prefix| (address_family, *prefix)));
+            }
+        }
+        prefixes
+    }
+
+    /// This function renders the update message as compact JSON object like [BGPMessage::to_json], but without the message type.
+    pub fn to_json(&self) -> String {
+        format!("{{{}}}", self.json_fields())
+    }
+
+    fn json_fields(&self) -> String {
+        format!(
+            r#""withdrawn_routes":{},"path_attributes":{},"nlri":{}"#,
+            json_array(&self.withdrawn_routes),
+            json_array(&self.path_attributes),
+            json_array(&self.network_layer_reachability_information)
+        )
+    }
+
+    /// This function returns the length of the update message's body without the BGP header like serialized by [UpdateMessage::pack].
+    pub fn wire_len(&self) -> usize {
+        self.wire_len_with(&SessionContext::four_octet_asn())
+    }
+
+    /// This function returns the length of the update message's body like serialized by [UpdateMessage::pack_with].
+    pub fn wire_len_with(&self, context: &SessionContext) -> usize {
+        4 + self.withdrawn_routes.iter().map(Prefix::wire_len).sum::<usize>()
+            + self.path_attributes.iter().map(|attribute| attribute.wire_len_with(context)).sum::<usize>()
+            + self.as4_path(context).map_or(0, |attribute| attribute.wire_len_with(context))
+            + self.network_layer_reachability_information.iter().map(Prefix::wire_len).sum::<usize>()
+    }
+
+    /// This function returns the AS4_PATH path attribute, which is added when serializing the message for a peer without four-octet AS
+    /// number support. It's only added if the AS_PATH contains AS numbers not fitting into two bytes and the message doesn't carry an
+    /// AS4_PATH path attribute already, like a received one.
+    ///
+    /// ## References
+    /// - [Interaction with NEW BGP Speakers, Section 4.2.2 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4.2.2)
+    fn as4_path(&self, context: &SessionContext) -> Option<PathAttribute> {
+        if context.four_octet_asn || self.path_attributes.iter().any(|attribute| attribute.kind() == AS4_PATH) {
+            return None;
+        }
+
+        self.path_attributes.iter().find_map(|attribute| match attribute {
+            PathAttribute::ASPath(segments) => rfc6793::as4_path(segments),
+            _ => None
+        })
+    }
+
+    /// This function serializes the update message's body without the BGP header.
+    pub fn pack(&self) -> Vec<u8> {
+        self.pack_with(&SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the update message's body like [UpdateMessage::pack], but with the encoding determined by the capabilities
+    /// negotiated in the session. This is the counterpart of [UpdateMessage::unpack_with].
+    pub fn pack_with(&self, context: &SessionContext) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.pack_into_with(&mut bytes, context);
+        bytes
+    }
+
+    /// This function serializes the update message's body and appends the bytes to the specified buffer. The lengths of the withdrawn
+    /// routes and the path attributes are patched in after writing them.
+    pub fn pack_into(&self, buffer: &mut Vec<u8>) {
+        self.pack_into_with(buffer, &SessionContext::four_octet_asn())
+    }
+
+    /// This function serializes the update message's body like [UpdateMessage::pack_with], but appends the bytes to the specified buffer.
+    pub fn pack_into_with(&self, buffer: &mut Vec<u8>, context: &SessionContext) {
+        let withdrawn_routes_start = buffer.len();
+        buffer.extend_from_slice(&[0, 0]);
+        self.withdrawn_routes.iter().for_each(|prefix| prefix.pack_into(buffer));
+        let withdrawn_routes_length = (buffer.len() - withdrawn_routes_start - 2) as u16;
+        buffer[withdrawn_routes_start..withdrawn_routes_start + 2].copy_from_slice(&withdrawn_routes_length.to_be_bytes());
+
+        let path_attributes_start = buffer.len();
+        buffer.extend_from_slice(&[0, 0]);
+        self.path_attributes.iter().for_each(|attribute| attribute.pack_into_with(buffer, context));
+        if let Some(attribute) = self.as4_path(context) {
+            attribute.pack_into_with(buffer, context);
+        }
+        let path_attributes_length = (buffer.len() - path_attributes_start - 2) as u16;
+        buffer[path_attributes_start..path_attributes_start + 2].copy_from_slice(&path_attributes_length.to_be_bytes());
+
+        self.network_layer_reachability_information.iter().for_each(|prefix| prefix.pack_into(buffer));
+    }
+
+    /// This function sorts the path attributes by their type code, like recommended by RFC 4271. The sort is stable, so multiple attributes
+    /// with the same type code keep their order. This should be called before packing the message to emit canonical update messages.
+    ///
+    /// ## References
+    /// - [UPDATE-Send Process, Section 5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-5)
+    pub fn sort_attributes(&mut self) {
+        self.path_attributes.sort_by_key(PathAttribute::kind);
+    }
+
+    /// This function returns whether the path attributes of both update messages are equivalent. Unlike the comparison with `==`, the
+    /// attributes are compared as a multiset, so the order of the path attributes and the order of the communities doesn't matter. This
+    /// can be used to distinguish genuine changes of a route from a peer re-sending the same attributes in a different order.
+    pub fn attributes_equivalent(&self, other: &Self) -> bool {
+        if self.path_attributes.len() != other.path_attributes.len() {
+            return false;
+        }
+
+        let mut remaining: Vec<PathAttribute> = other.path_attributes.iter().map(PathAttribute::normalized).collect();
+        self.path_attributes.iter().map(PathAttribute::normalized).all(|attribute| {
+            match remaining.iter().position(|other_attribute| *other_attribute == attribute) {
+                Some(index) => {
+                    remaining.swap_remove(index);
+                    true
+                },
+                None => false
+            }
+        })
+    }
+
+    /// This function applies the common outbound policy for advertising the routes of this update message to an external peer. The
+    /// MULTI_EXIT_DISC and LOCAL_PREF path attributes are removed, the local AS number is prepended to the AS path (confederation segments
+    /// are removed) and the next hop is rewritten to the specified address. An IPv4 next hop is set as the NEXT_HOP path attribute, while
+    /// the next hop of multiprotocol reachable NLRI is updated if it has the same address family as the specified address.
+    ///
+    /// ## References
+    /// - [UPDATE-Send Process, Section 9.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-9.2)
+    /// - [LOCAL_PREF, Section 5.1.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-5.1.5)
+    /// - [AS_PATH Modification Rules, Section 4.1 RFC 5065](https://datatracker.ietf.org/doc/html/rfc5065#section-4.1)
+    pub fn prepare_for_ebgp(&mut self, self_asn: u32, next_hop: IpAddr) {
+        self.path_attributes.retain(|attribute| !matches!(attribute, PathAttribute::MultiExitDisc(_) | PathAttribute::LocalPref(_)));
+        match self.path_attributes.iter_mut().find_map(|attribute| match attribute {
+            PathAttribute::ASPath(segments) => Some(segments),
+            _ => None
+        }) {
+            Some(segments) => {
+                segments.retain(|segment| !matches!(segment, ASPathSegment::ConfedSequence(_) | ASPathSegment::ConfedSet(_)));
+                match segments.first_mut() {
+                    Some(ASPathSegment::Sequence(asns)) if asns.len() < u8::MAX as usize => asns.insert(0, self_asn),
+                    _ => segments.insert(0, ASPathSegment::Sequence(vec![self_asn]))
+                }
+            },
+            None => self.path_attributes.push(PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![self_asn])]))
+        }
+
+        if let IpAddr::V4(address) = next_hop {
+            self.set_attribute(PathAttribute::NextHop(address));
+        }
+        self.path_attributes.iter_mut()
+            .filter_map(|attribute| match attribute {
+                PathAttribute::MpReachableNLRI(reachable) => Some(reachable),
+                _ => None
+            })
+            .filter(|reachable| reachable.next_hop_address.is_some_and(|address| address.address.is_ipv4() == next_hop.is_ipv4()))
+            .for_each(|reachable| reachable.next_hop_address = Some(MultiprotocolNextHop { address: next_hop, link_local_address: None }));
+    }
+
+    /// This function applies the common outbound policy for advertising the routes of this update message to an internal peer. The AS
+    /// path is left untouched and the LOCAL_PREF path attribute is set to the specified value.
+    ///
+    /// ## References
+    /// - [LOCAL_PREF, Section 5.1.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-5.1.5)
+    pub fn prepare_for_ibgp(&mut self, local_pref: u32) {
+        self.set_attribute(PathAttribute::LocalPref(local_pref));
+    }
+
+    /// This function replaces the path attribute with the same type code as the specified attribute or appends the attribute if no such
+    /// attribute is present in this update message.
+    fn set_attribute(&mut self, attribute: PathAttribute) {
+        match self.path_attributes.iter_mut().find(|other_attribute| other_attribute.kind() == attribute.kind()) {
+            Some(other_attribute) => *other_attribute = attribute,
+            None => self.path_attributes.push(attribute)
+        }
+    }
+
+    /// This function validates the path attributes of a received update message. Every path attribute may only appear once and the ORIGIN,
+    /// AS_PATH and NEXT_HOP path attributes must be present, if the update message contains NLRI. The first error found is returned.
+    ///
+    /// ## References
+    /// - [UPDATE Message Error Handling, Section 6.3 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.3)
+    pub fn validate_received(&self) -> Result<(), UpdateError> {
+        let mut kinds = HashSet::new();
+        if let Some(attribute) = self.path_attributes.iter().find(|attribute| !kinds.insert(attribute.kind())) {
+            return Err(UpdateError::MalformedAttributeList(attribute.kind()));
+        }
+
+        if self.network_layer_reachability_information.is_empty() {
+            return Ok(());
+        }
+
+        match [0x01, 0x02, 0x03].into_iter().find(|kind| !kinds.contains(kind)) {
+            Some(kind) => Err(UpdateError::MissingWellKnownAttribute(kind)),
+            None => Ok(())
+        }
+    }
+
+    /// This function validates the withdrawn routes length and total path attribute length fields of a received update message's body
+    /// against the available input. Unlike the parser, which fails with a generic error, this function reports which length field declares
+    /// more bytes than available. A missing length field is reported with the two bytes of the field itself as declared length.
+    ///
+    /// ## References
+    /// - [UPDATE Message Error Handling, Section 6.3 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.3)
+    pub fn validate_length_fields(input: &[u8]) -> Result<(), UpdateError> {
+        let mut input = input;
+        for field in ["withdrawn routes", "path attributes"] {
+            let [high, low, remaining @ ..] = input else {
+                return Err(UpdateError::LengthFieldMismatch { field, declared: 2, available: input.len() });
+            };
+
+            let declared = u16::from_be_bytes([*high, *low]) as usize;
+            if declared > remaining.len() {
+                return Err(UpdateError::LengthFieldMismatch { field, declared, available: remaining.len() });
+            }
+            input = &remaining[declared..];
+        }
+        Ok(())
+    }
+
+    /// This function returns the value of the LOCAL_PREF path attribute, if present in this update message.
+    pub fn local_pref(&self) -> Option<u32> {
+        self.path_attributes.iter().find_map(|attribute| match attribute {
+            PathAttribute::LocalPref(value) => Some(*value),
+            _ => None
+        })
+    }
+
+    /// This function returns the value of the MULTI_EXIT_DISC path attribute, if present in this update message.
+    pub fn med(&self) -> Option<u32> {
+        self.path_attributes.iter().find_map(|attribute| match attribute {
+            PathAttribute::MultiExitDisc(value) => Some(*value),
+            _ => None
+        })
+    }
+
+    /// This function returns the value of the ORIGIN path attribute, if present in this update message.
+    pub fn origin(&self) -> Option<Origin> {
+        self.path_attributes.iter().find_map(|attribute| match attribute {
+            PathAttribute::Origin(origin) => Some(*origin),
+            _ => None
+        })
+    }
+
+    /// This function returns the length of the AS_PATH path attribute used in the route selection or zero if no AS path is present.
+    pub fn as_path_len(&self) -> usize {
+        self.path_attributes.iter()
+            .filter_map(|attribute| match attribute {
+                PathAttribute::ASPath(segments) => Some(segments.iter().map(ASPathSegment::path_length).sum::<usize>()),
+                _ => None
+            })
+            .sum()
+    }
+
+    /// This function returns whether the specified AS number is in any segment of the AS_PATH path attribute of this update message. When
+    /// importing routes from an external peer, routes containing the own AS number must be dropped to avoid routing loops.
+    pub fn contains_asn(&self, asn: u32) -> bool {
+        self.path_attributes.iter().any(|attribute| match attribute {
+            PathAttribute::ASPath(segments) => path_attr::all_asns(segments).any(|other_asn| other_asn == asn),
+            _ => false
+        })
+    }
+
+    /// This function returns whether the specified cluster ID is already in the CLUSTER_LIST path attribute of this update message. A route
+    /// reflector must ignore such routes, as they were already reflected by its cluster and would cause a routing loop otherwise.
+    ///
+    /// ## References
+    /// - [Avoiding Routing Information Loops, Section 8 RFC 4456](https://datatracker.ietf.org/doc/html/rfc4456#section-8)
+    pub fn has_loop(&self, cluster_id: Ipv4Addr) -> bool {
+        self.path_attributes.iter().any(|attribute| match attribute {
+            PathAttribute::ClusterList(cluster_ids) => cluster_ids.contains(&cluster_id),
+            _ => false
+        })
+    }
+
+    /// This function returns the preference of the routes announced in this update message, which can be compared to the preference of
+    /// other routes to the same destination.
+    pub fn path_preference(&self) -> PathPreference {
+        PathPreference {
+            local_pref: self.local_pref().unwrap_or(PathPreference::DEFAULT_LOCAL_PREF),
+            as_path_len: self.as_path_len(),
+            origin: self.origin().unwrap_or(Origin::Incomplete),
+            med: self.med().unwrap_or(0)
+        }
+    }
+}
+
+impl Display for UpdateMessage {
+    /// This function formats a one-line summary of the update message for the logs. The counts of announced and withdrawn prefixes include
+    /// the prefixes of the multiprotocol path attributes.
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let (mut announced, mut withdrawn) = (self.network_layer_reachability_information.len(), self.withdrawn_routes.len());
+        for attribute in &self.path_attributes {
+            match attribute {
+                PathAttribute::MpReachableNLRI(reachable) => announced += reachable.route_count(),
+                PathAttribute::MpUnreachableNLRI(unreachable) => withdrawn += unreachable.route_count(),
+                _ => {}
+            }
+        }
+
+        let attributes: Vec<String> = self.path_attributes.iter().map(PathAttribute::to_string).collect();
+        write!(formatter, "UPDATE: +{} NLRI, -{} withdrawn, attrs=[{}]", announced, withdrawn, attributes.join(", "))
+    }
+}
+
+/// This function builds the update messages advertising the specified routes. Routes with equivalent path attributes (compared like
+/// [UpdateMessage::attributes_equivalent]) are advertised in a single update message, which is split into multiple update messages if it
+/// would exceed [MAX_MESSAGE_LENGTH] bytes. The update messages are ordered by the first route of their group.
+///
+/// IPv4 routes are advertised in the NLRI of the update message. IPv6 routes are advertised in the NLRI of the MP_REACH_NLRI path
+/// attribute, so their path attributes must contain an MP_REACH_NLRI path attribute for IPv6 carrying the next hop. An error is returned
+/// if this attribute is missing or if the path attributes leave no space for a single route in the message.
+///
+/// ## References
+/// - [Use of BGP-4 Multiprotocol Extensions for IPv6, Section 2 RFC 2545](https://datatracker.ietf.org/doc/html/rfc2545#section-2)
+pub fn build_updates(routes: &[(Prefix, Vec<PathAttribute>)]) -> Result<Vec<UpdateMessage>, PackError> {
+    let mut groups: Vec<(AddressFamily, UpdateMessage)> = Vec::new();
+    for (prefix, path_attributes) in routes {
+        let update = UpdateMessage {
+            withdrawn_routes: Vec::new(),
+            path_attributes: path_attributes.clone(),
+            network_layer_reachability_information: vec![*prefix]
+        };
+        match groups.iter_mut().find(|(family, group)| *family == prefix.family() && group.attributes_equivalent(&update)) {
+            Some((_, group)) => group.network_layer_reachability_information.push(*prefix),
+            None => groups.push((prefix.family(), update))
+        }
+    }
+
+    let mut updates: Vec<UpdateMessage> = Vec::new();
+    for (family, group) in groups {
+        let reachable_index = match family {
+            AddressFamily::IPv4 => None,
+            _ => Some(group.path_attributes.iter()
+                .position(|attribute| matches!(attribute, PathAttribute::MpReachableNLRI(reachable) if reachable.address_family == family))
+                .ok_or(PackError::MissingAttribute { element: "IPv6 route", kind: 14 })?)
+        };
+
+        // Every message contains the header, the lengths of the withdrawn routes and path attributes and the path attributes. The
+        // MP_REACH_NLRI path attribute may need the extended length after adding the routes, so one byte is reserved for it.
+        let base_length = BGPMessageHeader::LENGTH + 4 + group.path_attributes.iter().map(PathAttribute::wire_len).sum::<usize>()
+            + usize::from(reachable_index.is_some());
+        let start = updates.len();
+        let mut length = 0;
+        for prefix in group.network_layer_reachability_information {
+            let prefix_length = prefix.wire_len();
+            if updates.len() == start || length + prefix_length > MAX_MESSAGE_LENGTH {
+                check_length("Message", base_length + prefix_length, MAX_MESSAGE_LENGTH)?;
+                updates.push(UpdateMessage {
+                    withdrawn_routes: Vec::new(),
+                    path_attributes: group.path_attributes.clone(),
+                    network_layer_reachability_information: Vec::new()
+                });
+                length = base_length;
+            }
+
+            let update = updates.last_mut().unwrap();
+            match reachable_index.map(|index| &mut update.path_attributes[index]) {
+                Some(PathAttribute::MpReachableNLRI(reachable)) => reachable.network_layer_reachability_information.push(prefix),
+                _ => update.network_layer_reachability_information.push(prefix)
+            }
+            length += prefix_length;
+        }
+    }
+    Ok(updates)
+}
+
+/// This struct contains the values of the path attributes used by the decision process to select the best route to a destination. The
+/// ordering of this struct implements the tie-breaking rules of RFC 4271, so the more preferred route is always ordered before the less
+/// preferred route. Sorting a list of path preferences puts the best route first. The attributes are compared in the following order:
+/// - Highest LOCAL_PREF
+/// - Shortest AS_PATH
+/// - Lowest ORIGIN (IGP before EGP before Incomplete)
+/// - Lowest MULTI_EXIT_DISC (compared regardless of the neighbor AS)
+///
+/// ## References
+/// - [Phase 2: Route Selection, Section 9.1.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-9.1.2)
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Copy)]
+pub struct PathPreference {
+    pub local_pref: u32,
+    pub as_path_len: usize,
+    pub origin: Origin,
+    pub med: u32
+}
+
+impl PathPreference {
+    /// The LOCAL_PREF assumed for routes without a LOCAL_PREF path attribute
+    pub const DEFAULT_LOCAL_PREF: u32 = 100;
+}
+
+impl Ord for PathPreference {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.local_pref.cmp(&self.local_pref)
+            .then(self.as_path_len.cmp(&other.as_path_len))
+            .then(self.origin.cmp(&other.origin))
+            .then(self.med.cmp(&other.med))
+    }
+}
+
+impl PartialOrd for PathPreference {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// This enum represents the subcodes of the Message Header Error (error code 1) NOTIFICATION.
+///
+/// ## References
+/// - [NOTIFICATION Message Format, Section 4.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.5)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+#[repr(u8)]
+pub enum MessageHeaderErrorSubcode {
+    ConnectionNotSynchronized = 1,
+    BadMessageLength = 2,
+    BadMessageType = 3
+}
+
+/// This enum represents the subcodes of the OPEN Message Error (error code 2) NOTIFICATION.
+///
+/// ## References
+/// - [NOTIFICATION Message Format, Section 4.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.5)
+/// - [Extensions to Error Handling, Section 4 RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392#section-4)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+#[repr(u8)]
+pub enum OpenErrorSubcode {
+    UnsupportedVersionNumber = 1,
+    BadPeerAS = 2,
+    BadBgpIdentifier = 3,
+    UnsupportedOptionalParameter = 4,
+    UnacceptableHoldTime = 6,
+    UnsupportedCapability = 7
+}
+
+/// This enum represents the subcodes of the UPDATE Message Error (error code 3) NOTIFICATION.
+///
+/// ## References
+/// - [NOTIFICATION Message Format, Section 4.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.5)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+#[repr(u8)]
+pub enum UpdateErrorSubcode {
+    MalformedAttributeList = 1,
+    UnrecognizedWellKnownAttribute = 2,
+    MissingWellKnownAttribute = 3,
+    AttributeFlagsError = 4,
+    AttributeLengthError = 5,
+    InvalidOriginAttribute = 6,
+    InvalidNextHopAttribute = 8,
+    OptionalAttributeError = 9,
+    InvalidNetworkField = 10,
+    MalformedASPath = 11
+}
+
+/// This enum represents the subcodes of the Cease (error code 6) NOTIFICATION.
+///
+/// ## References
+/// - [Subcode Definitions, Section 4 RFC 4486](https://datatracker.ietf.org/doc/html/rfc4486#section-4)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+#[repr(u8)]
+pub enum CeaseSubcode {
+    MaximumNumberOfPrefixesReached = 1,
+    AdministrativeShutdown = 2,
+    PeerDeconfigured = 3,
+    AdministrativeReset = 4,
+    ConnectionRejected = 5,
+    OtherConfigurationChange = 6,
+    ConnectionCollisionResolution = 7,
+    OutOfResources = 8
 }
 
 /// This struct is the type-safe implementation for handling the incoming/outgoing notification message. The notification message informs
@@ -280,17 +1881,129 @@ impl UpdateMessage {
 ///
 /// ## Reference
 /// - [NOTIFICATION Message Format, Section 4.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.5)
-#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct NotificationMessage {
     pub error_code: u8,
     pub error_subcode: u8,
     pub data: Vec<u8>
 }
 
+impl Debug for NotificationMessage {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.debug_struct("NotificationMessage")
+            .field("error_code", &self.error_code)
+            .field("error_subcode", &self.error_subcode)
+            .field("data", &HexDump(&self.data))
+            .finish()
+    }
+}
+
 impl NotificationMessage {
+    /// This function creates a Message Header Error NOTIFICATION (error code 1) with the specified subcode and data.
+    pub fn message_header_error(subcode: MessageHeaderErrorSubcode, data: Vec<u8>) -> Self {
+        Self { error_code: 1, error_subcode: subcode as u8, data }
+    }
+
+    /// This function creates an OPEN Message Error NOTIFICATION (error code 2) with the specified subcode and without data.
+    pub fn open_error(subcode: OpenErrorSubcode) -> Self {
+        Self { error_code: 2, error_subcode: subcode as u8, data: Vec::new() }
+    }
+
+    /// This function creates an UPDATE Message Error NOTIFICATION (error code 3) with the specified subcode and data.
+    pub fn update_error(subcode: UpdateErrorSubcode, data: Vec<u8>) -> Self {
+        Self { error_code: 3, error_subcode: subcode as u8, data }
+    }
+
+    /// This function creates a Hold Timer Expired NOTIFICATION (error code 4).
+    pub fn hold_timer_expired() -> Self {
+        Self { error_code: 4, error_subcode: 0, data: Vec::new() }
+    }
+
+    /// This function creates a Finite State Machine Error NOTIFICATION (error code 5).
+    pub fn fsm_error() -> Self {
+        Self { error_code: 5, error_subcode: 0, data: Vec::new() }
+    }
+
+    /// This function creates a Cease NOTIFICATION (error code 6) with the specified subcode and without data.
+    pub fn cease(subcode: CeaseSubcode) -> Self {
+        Self { error_code: 6, error_subcode: subcode as u8, data: Vec::new() }
+    }
+
     fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
         let (input, error_code) = be_u8(input)?;
         let (data, error_subcode) = be_u8(input)?;
         Ok((&[], Self { error_code, error_subcode, data: data.to_vec() }))
     }
+
+    /// This function returns the length of the notification message's body without the BGP header.
+    pub fn wire_len(&self) -> usize {
+        2 + self.data.len()
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        let mut bytes = vec![self.error_code, self.error_subcode];
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// This function decodes the data of this notification, if this notification is an OPEN Message Error (error code 2). If the subcode
+    /// is unknown or the data doesn't match the subcode's format, this function returns none.
+    ///
+    /// ## References
+    /// - [NOTIFICATION Message Format, Section 4.5 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.5)
+    /// - [OPEN Message Error Handling, Section 6.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.2)
+    /// - [Extensions to Error Handling, Section 4 RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392#section-4)
+    pub fn open_error_detail(&self) -> Option<OpenErrorDetail> {
+        if self.error_code != 2 {
+            return None;
+        }
+
+        Some(match self.error_subcode {
+            1 => OpenErrorDetail::UnsupportedVersionNumber(u16::from_be_bytes(self.data.as_slice().try_into().ok()?)),
+            2 => OpenErrorDetail::BadPeerAS,
+            3 => OpenErrorDetail::BadBgpIdentifier,
+            4 => OpenErrorDetail::UnsupportedOptionalParameter,
+            6 => OpenErrorDetail::UnacceptableHoldTime(u16::from_be_bytes(self.data.as_slice().try_into().ok()?)),
+            7 => match many_bounded(MAX_CAPABILITIES, Capability::unpack)(&self.data).ok()? {
+                ([], capabilities) => OpenErrorDetail::UnsupportedCapability(capabilities),
+                _ => return None
+            },
+            _ => return None
+        })
+    }
+}
+
+/// This enum represents the decoded data of a NOTIFICATION for an OPEN Message Error, which tells why the peer rejected our open message.
+///
+/// ## References
+/// - [OPEN Message Error Handling, Section 6.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-6.2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum OpenErrorDetail {
+    /// The version is not supported by the peer, the value is the largest locally-supported version number less than the version sent
+    UnsupportedVersionNumber(u16),
+    BadPeerAS,
+    BadBgpIdentifier,
+    UnsupportedOptionalParameter,
+
+    /// The hold time sent is not accepted by the peer, the value is the rejected hold time
+    UnacceptableHoldTime(u16),
+
+    /// The peer doesn't support the listed capabilities, which are required by us
+    UnsupportedCapability(Vec<Capability>)
+}
+
+impl Display for OpenErrorDetail {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedVersionNumber(version) => write!(formatter, "Unsupported version number (Supported: {})", version),
+            Self::BadPeerAS => write!(formatter, "Bad peer AS"),
+            Self::BadBgpIdentifier => write!(formatter, "Bad BGP identifier"),
+            Self::UnsupportedOptionalParameter => write!(formatter, "Unsupported optional parameter"),
+            Self::UnacceptableHoldTime(hold_time) => write!(formatter, "Unacceptable hold time of {} seconds", hold_time),
+            Self::UnsupportedCapability(capabilities) => {
+                let capabilities: Vec<String> = capabilities.iter().map(ToString::to_string).collect();
+                write!(formatter, "Unsupported capabilities [{}]", capabilities.join(", "))
+            }
+        }
+    }
 }
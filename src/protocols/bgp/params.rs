@@ -12,29 +12,155 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt::{Display, Formatter};
 use nom::bytes::complete::take;
 use nom::IResult;
-use nom::multi::many0;
-use nom::number::complete::be_u8;
+use nom::number::complete::{be_u16, be_u8};
 use crate::protocols::bgp::rfc3392::Capability;
-use nom::Parser;
+use crate::protocols::bgp::{check_length, many_bounded, PackError, MAX_CAPABILITIES};
 
 /// This enum implements all optional parameters which are sent with the BGP open message. These parameters contains some information about
 /// the router and it's capabilities ([RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392)).<
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum OptionalParameter {
+    /// This value indicates the deprecated authentication parameter (Type 1), which is still sent by some old implementations. It's only
+    /// recognized to make the parameter visible in diagnostics, the authentication data isn't verified.
+    ///
+    /// ## References
+    /// - [OPEN Message Format, Section 4.2 RFC 1771](https://datatracker.ietf.org/doc/html/rfc1771#section-4.2)
+    /// - [Changes from RFC 1771, Appendix A RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#appendix-A)
+    Authentication { auth_code: u8, data: Vec<u8> },
+
     Capabilities(Vec<Capability>),
     Unknown { kind: u8, data: Vec<u8> }
 }
 
 impl OptionalParameter {
-    pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
-        let (input, kind) = be_u8(input)?;
-        let (input, length) = be_u8(input)?;
+    /// This function parses a single optional parameter. The `extended` parameter is set true, if the optional parameters are encoded with
+    /// the extended length of RFC 9072. In this case, the length of each parameter is two bytes instead of one byte.
+    ///
+    /// ## References
+    /// - [Extended Optional Parameters Length, Section 2 RFC 9072](https://datatracker.ietf.org/doc/html/rfc9072#section-2)
+    pub(crate) fn unpack(input: &[u8], extended: bool) -> IResult<&[u8], Self> {
+        let (input, (kind, length)) = Self::unpack_header(input, extended)?;
         let (input, data) = take(length)(input)?;
-        Ok((input, match kind {
-            2 => Self::Capabilities(many0(Capability::unpack).parse(data)?.1),
-            _ => Self::Unknown { kind, data: data.to_vec() }
-        }))
+        Ok((input, Self::unpack_data(kind, data)?.1))
+    }
+
+    fn unpack_header(input: &[u8], extended: bool) -> IResult<&[u8], (u8, u16)> {
+        let (input, kind) = be_u8(input)?;
+        let (input, length) = if extended { be_u16(input)? } else {
+            let (input, length) = be_u8(input)?;
+            (input, length as u16)
+        };
+        Ok((input, (kind, length)))
+    }
+
+    fn unpack_data(kind: u8, data: &[u8]) -> IResult<&[u8], Self> {
+        Ok(match kind {
+            1 => {
+                let (data, auth_code) = be_u8(data)?;
+                (&[], Self::Authentication { auth_code, data: data.to_vec() })
+            },
+            2 => {
+                let (remaining, capabilities) = many_bounded(MAX_CAPABILITIES, Capability::unpack)(data)?;
+                (remaining, Self::Capabilities(capabilities))
+            },
+            _ => (&[], Self::Unknown { kind, data: data.to_vec() })
+        })
+    }
+
+    /// This function returns the length of the serialized optional parameter like [OptionalParameter::pack] with the same `extended`
+    /// parameter.
+    pub(crate) fn wire_len(&self, extended: bool) -> usize {
+        let data_length = match self {
+            Self::Authentication { data, .. } => 1 + data.len(),
+            Self::Capabilities(capabilities) => capabilities.iter().map(Capability::wire_len).sum(),
+            Self::Unknown { data, .. } => data.len()
+        };
+        if extended { 3 + data_length } else { 2 + data_length }
+    }
+
+    /// This function serializes the optional parameter. The `extended` parameter is set true, if the length of the parameter should be
+    /// encoded with two bytes like specified by RFC 9072.
+    pub(crate) fn pack(&self, extended: bool) -> Vec<u8> {
+        let (kind, data) = match self {
+            Self::Authentication { auth_code, data } => (1, [&[*auth_code], data.as_slice()].concat()),
+            Self::Capabilities(capabilities) => (2, capabilities.iter().flat_map(Capability::pack).collect()),
+            Self::Unknown { kind, data } => (*kind, data.clone())
+        };
+
+        let mut bytes = vec![kind];
+        if extended {
+            bytes.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        } else {
+            bytes.push(data.len() as u8);
+        }
+        bytes.extend(data);
+        bytes
+    }
+}
+
+impl OptionalParameter {
+    /// This function returns an error, if the parameter's data or the data of one of its capabilities doesn't fit into its length field.
+    /// The length of the parameter itself is limited to two bytes, as the extended length is used for long parameters. Without the
+    /// `rfc9072` feature, the length is limited to a single byte.
+    pub(crate) fn check_lengths(&self) -> Result<(), PackError> {
+        if let Self::Capabilities(capabilities) = self {
+            capabilities.iter().try_for_each(Capability::check_lengths)?;
+        }
+
+        let maximum = if cfg!(feature = "rfc9072") { u16::MAX as usize } else { u8::MAX as usize };
+        check_length("Optional parameter", self.wire_len(false) - 2, maximum)
+    }
+}
+
+/// This enum represents an optional parameter, which was skipped while parsing an open message leniently with
+/// [OpenMessage::unpack_lenient](crate::protocols::bgp::OpenMessage::unpack_lenient).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParamParseError {
+    /// This value indicates a parameter whose length exceeds the remaining optional parameters. All following parameters are lost, as the
+    /// start of the next parameter is unknown.
+    Truncated { kind: Option<u8>, length: Option<u16>, available: usize },
+
+    /// This value indicates a parameter whose data couldn't be parsed completely. The parsing continues with the next parameter.
+    Malformed { kind: u8, data: Vec<u8> }
+}
+
+impl Display for ParamParseError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated { kind: Some(kind), length: Some(length), available } => {
+                write!(formatter, "Parameter of kind {} is {} bytes long, but only {} bytes are remaining", kind, length, available)
+            },
+            Self::Truncated { available, .. } => write!(formatter, "Unable to parse parameter header out of {} bytes", available),
+            Self::Malformed { kind, data } => write!(formatter, "Malformed parameter of kind {} with {} bytes", kind, data.len())
+        }
+    }
+}
+
+impl std::error::Error for ParamParseError {}
+
+/// This function parses the optional parameters of an open message, but skips parameters which can't be parsed instead of failing. The
+/// successfully parsed parameters are returned with the errors of the skipped parameters.
+pub(crate) fn unpack_lenient(mut input: &[u8], extended: bool) -> (Vec<OptionalParameter>, Vec<ParamParseError>) {
+    let mut parameters = Vec::new();
+    let mut errors = Vec::new();
+    while !input.is_empty() {
+        let Ok((remaining, (kind, length))) = OptionalParameter::unpack_header(input, extended) else {
+            errors.push(ParamParseError::Truncated { kind: input.first().copied(), length: None, available: input.len() });
+            break;
+        };
+        let Ok((remaining, data)) = take::<_, _, nom::error::Error<&[u8]>>(length)(remaining) else {
+            errors.push(ParamParseError::Truncated { kind: Some(kind), length: Some(length), available: remaining.len() });
+            break;
+        };
+
+        match OptionalParameter::unpack_data(kind, data) {
+            Ok((&[], parameter)) => parameters.push(parameter),
+            _ => errors.push(ParamParseError::Malformed { kind, data: data.to_vec() })
+        }
+        input = remaining;
     }
+    (parameters, errors)
 }
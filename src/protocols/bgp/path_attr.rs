@@ -12,6 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt::{Display, Formatter};
+use nom::{IResult, Parser};
+use nom::error::{Error, ErrorKind};
+use nom::multi::count;
+use nom::combinator::map;
+use nom::number::complete::{be_u16, be_u32, be_u8};
+use crate::protocols::bgp::{check_length, PackError};
+use crate::protocols::bgp::rfc6793::AS_TRANS;
+
 /// The origin attribute is one of the mandatory attributes when sending update messages and is informing about the origin of the
 /// NLRI/prefixes sent in the message.
 #[repr(u8)]
@@ -37,4 +46,151 @@ impl From<u8> for Origin {
     }
 }
 
-// TODO: Add AS_PATH, NEXT_HOP and other specified by https://datatracker.ietf.org/doc/html/rfc4271#section-5
+/// This enum represents a single segment of the AS_PATH attribute. The AS path is the list of autonomous systems the routes of an update
+/// message have traversed. The AS numbers are handled as four-octet AS numbers, unless the session context specifies that the four-octet
+/// AS number support ([RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793)) wasn't negotiated with the peer.
+///
+/// ## References
+/// - [AS_PATH, Section 4.3 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-4.3)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ASPathSegment {
+    /// Unordered set of ASes a route in the UPDATE message has traversed
+    Set(Vec<u32>),
+
+    /// Ordered set of ASes a route in the UPDATE message has traversed
+    Sequence(Vec<u32>),
+
+    /// Ordered set of member ASes in the local confederation the route has traversed
+    ///
+    /// ## References
+    /// - [AS_CONFED_SEQUENCE and AS_CONFED_SET, Section 3 RFC 5065](https://datatracker.ietf.org/doc/html/rfc5065#section-3)
+    ConfedSequence(Vec<u32>),
+
+    /// Unordered set of member ASes in the local confederation the route has traversed
+    ///
+    /// ## References
+    /// - [AS_CONFED_SEQUENCE and AS_CONFED_SET, Section 3 RFC 5065](https://datatracker.ietf.org/doc/html/rfc5065#section-3)
+    ConfedSet(Vec<u32>),
+
+    /// This value indicates a segment with an unknown segment type
+    Unknown { kind: u8, asns: Vec<u32> }
+}
+
+impl ASPathSegment {
+    /// This function reads a segment from the input with four-octet or two-octet AS numbers, depending on whether the four-octet AS number
+    /// support was negotiated. If the input is shorter than the count of AS numbers declared by the segment, the parser fails before
+    /// reading any AS number.
+    pub(crate) fn unpack_with(input: &[u8], four_octet_asn: bool) -> IResult<&[u8], Self> {
+        let (input, kind) = be_u8(input)?;
+        let (input, length) = be_u8(input)?;
+        let width = if four_octet_asn { 4 } else { 2 };
+        if input.len() < length as usize * width {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Eof)));
+        }
+
+        let (input, asns) = if four_octet_asn {
+            count(be_u32, length as usize).parse(input)?
+        } else {
+            count(map(be_u16, u32::from), length as usize).parse(input)?
+        };
+        Ok((input, match kind {
+            1 => Self::Set(asns),
+            2 => Self::Sequence(asns),
+            3 => Self::ConfedSequence(asns),
+            4 => Self::ConfedSet(asns),
+            _ => Self::Unknown { kind, asns }
+        }))
+    }
+
+    /// This function serializes the segment with four-octet or two-octet AS numbers, depending on whether the four-octet AS number support
+    /// was negotiated. With two-octet AS numbers, AS numbers not fitting into two bytes are replaced by [AS_TRANS], the original AS numbers
+    /// are sent in the AS4_PATH path attribute added by the update message. The count of AS numbers is a single byte, so segments with more
+    /// than 255 AS numbers are split into multiple segments of the same type. No AS number is dropped, but a split set counts as one AS per
+    /// resulting set in the path length, so [PathAttribute::try_pack](crate::protocols::bgp::PathAttribute::try_pack) rejects these sets
+    /// (see [ASPathSegment::check_lengths]).
+    ///
+    /// ## References
+    /// - [Interaction with NEW BGP Speakers, Section 4.2.2 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4.2.2)
+    pub(crate) fn pack_with(&self, four_octet_asn: bool) -> Vec<u8> {
+        let (kind, asns) = match self {
+            Self::Set(asns) => (1, asns),
+            Self::Sequence(asns) => (2, asns),
+            Self::ConfedSequence(asns) => (3, asns),
+            Self::ConfedSet(asns) => (4, asns),
+            Self::Unknown { kind, asns } => (*kind, asns)
+        };
+
+        let mut bytes = Vec::with_capacity(self.wire_len_with(four_octet_asn));
+        for asns in asns.chunks(u8::MAX as usize) {
+            bytes.extend_from_slice(&[kind, asns.len() as u8]);
+            for asn in asns {
+                match four_octet_asn {
+                    true => bytes.extend_from_slice(&asn.to_be_bytes()),
+                    false => bytes.extend_from_slice(&u16::try_from(*asn).unwrap_or(AS_TRANS).to_be_bytes())
+                }
+            }
+        }
+        bytes
+    }
+
+    /// This function returns the length of the serialized segment like [ASPathSegment::pack_with], including the headers of the split
+    /// segments.
+    pub(crate) fn wire_len_with(&self, four_octet_asn: bool) -> usize {
+        let count = self.asns().len();
+        count.div_ceil(u8::MAX as usize) * 2 + count * if four_octet_asn { 4 } else { 2 }
+    }
+
+    /// This function returns an error, if the segment is a set with more than 255 AS numbers. Unlike sequences, sets can't be split into
+    /// multiple segments without changing the length of the path.
+    pub(crate) fn check_lengths(&self) -> Result<(), PackError> {
+        match self {
+            Self::Sequence(_) | Self::ConfedSequence(_) => Ok(()),
+            _ => check_length("AS_PATH segment", self.asns().len(), u8::MAX as usize)
+        }
+    }
+
+    /// This function returns the AS numbers of this segment regardless of the segment type.
+    pub fn asns(&self) -> &[u32] {
+        match self {
+            Self::Set(asns) | Self::Sequence(asns) | Self::ConfedSequence(asns) | Self::ConfedSet(asns) | Self::Unknown { asns, .. } => asns
+        }
+    }
+
+    /// This function returns the length of the segment used in the route selection. Following to RFC 4271, an AS_SET counts as one AS
+    /// regardless of the ASes in the set. Confederation segments don't count towards the path length (RFC 5065).
+    ///
+    /// ## References
+    /// - [Breaking Ties (Phase 2), Section 9.1.2.2 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-9.1.2.2)
+    /// - [AS_PATH and Path Selection, Section 5.3 RFC 5065](https://datatracker.ietf.org/doc/html/rfc5065#section-5.3)
+    pub fn path_length(&self) -> usize {
+        match self {
+            Self::ConfedSequence(_) | Self::ConfedSet(_) => 0,
+            Self::Set(_) => 1,
+            Self::Sequence(asns) | Self::Unknown { asns, .. } => asns.len()
+        }
+    }
+}
+
+impl Display for ASPathSegment {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let asns: Vec<String> = self.asns().iter().map(u32::to_string).collect();
+        match self {
+            Self::Set(_) => write!(formatter, "{{{}}}", asns.join(" ")),
+            Self::Sequence(_) => write!(formatter, "{}", asns.join(" ")),
+            Self::ConfedSequence(_) => write!(formatter, "({})", asns.join(" ")),
+            Self::ConfedSet(_) => write!(formatter, "[{}]", asns.join(" ")),
+            Self::Unknown { kind, .. } => write!(formatter, "({}: {})", kind, asns.join(" "))
+        }
+    }
+}
+
+/// This function returns an iterator over the AS numbers of all segments of the AS path, flattening sequences and sets (including the
+/// confederation segments). This is used for the loop detection, which checks whether the own AS number is already in the AS path.
+///
+/// ## References
+/// - [Phase 1: Calculation of Degree of Preference, Section 9.1.1 RFC 4271](https://datatracker.ietf.org/doc/html/rfc4271#section-9.1.1)
+pub fn all_asns(segments: &[ASPathSegment]) -> impl Iterator<Item = u32> + '_ {
+    segments.iter().flat_map(|segment| segment.asns().iter().copied())
+}
+
+// TODO: Add ATOMIC_AGGREGATE, AGGREGATOR and other specified by https://datatracker.ietf.org/doc/html/rfc4271#section-5
@@ -20,13 +20,16 @@
 //! - `65535:65281` (`NO_EXPORT`) - All routes received with this community **MUST NOT** be advertised outside a BGP confederation
 //! - `65535:65282` (`NO_ADVERTISE`) - All routes received with this community **MUST NOT** be advertised to other BGP peers
 //! - `65535:65283` (`NO_EXPORT_SUBCONFED`) - ALl routes received with this community **MUST NOT** be advertised to eBGP peers
+//! - `65535:6` (`LLGR_STALE`) - All routes with this community are stale routes retained by the Long-Lived Graceful Restart
 //!
 //! ## References
 //! - [RFC 1997 "BGP Communities Attribute"](https://datatracker.ietf.org/doc/html/rfc1997)
 //! - [RFC 4360 "BGP Extended Communities Attribute"](https://datatracker.ietf.org/doc/html/rfc4360)
 //! - [RFC 5668 "4-Octet AS-specific BGP Extended Community"](https://datatracker.ietf.org/doc/html/rfc5668)
 
+use std::fmt::{Display, Formatter};
 use std::net::Ipv4Addr;
+use std::str::FromStr;
 use bitflags::bitflags;
 use nom::{IResult, bytes::complete::take, number::complete::{be_u16, be_u32, be_u8}};
 
@@ -111,10 +114,17 @@ pub enum Community {
     /// - [4-Octet AS Specific Extended Community, Section 2 RFC 5668](https://datatracker.ietf.org/doc/html/rfc5668#section-2)
     RFC5668ASN { subkind: Assignment, flags: CommunityFlags, global_administrator: u32, local_administrator: u16 },
 
-    Unknown { kind: u8, subkind: Assignment, flags: CommunityFlags }
+    Unknown { kind: u8, subkind: Assignment, flags: CommunityFlags, value: [u8; 6] }
 }
 
 impl Community {
+    /// The well-known community marking the stale routes retained by the Long-Lived Graceful Restart, which are less preferred than all
+    /// other routes.
+    ///
+    /// ## References
+    /// - [LLGR_STALE Community, Section 4.2 RFC 9494](https://datatracker.ietf.org/doc/html/rfc9494#section-4.2)
+    pub const LLGR_STALE: Community = Community::RFC1997 { global_administrator: 65535, local_administrator: 6 };
+
     /// This function takes the input bytes and serializes them into a community. The `extended_attribute` parameter is set true, if this
     /// element is being parsed in an extended communities path attribute, otherwise that should be set false. If successful, this function
     /// returns the remaining bytes as a slice and the community itself.
@@ -154,8 +164,127 @@ impl Community {
                     let (input, value) = take(6usize)(input)?;
                     Ok((input, Self::RFC4360Opaque { subkind, flags, value: value.try_into().unwrap() }))
                 },
-                _ => Ok((input, Self::Unknown { kind, subkind, flags }))
+                _ => {
+                    let (input, value) = take(6usize)(input)?;
+                    Ok((input, Self::Unknown { kind, subkind, flags, value: value.try_into().unwrap() }))
+                }
             }
         }
     }
+
+    /// This function returns the route target of this community, if this community is an extended community with the route target
+    /// subtype. Other communities return [None].
+    pub fn as_route_target(&self) -> Option<RouteTarget> {
+        match *self {
+            Self::RFC4360ASN { subkind: Assignment::RouteTarget, global_administrator, local_administrator, .. } => {
+                Some(RouteTarget::ASN { asn: global_administrator as u32, value: local_administrator })
+            },
+            Self::RFC5668ASN { subkind: Assignment::RouteTarget, global_administrator, local_administrator, .. } => {
+                Some(RouteTarget::ASN { asn: global_administrator, value: local_administrator as u32 })
+            },
+            Self::RFC4360Address { subkind: Assignment::RouteTarget, global_administrator, local_administrator, .. } => {
+                Some(RouteTarget::Address { address: global_administrator, value: local_administrator })
+            },
+            _ => None
+        }
+    }
+
+    /// This function returns the length of the serialized community, which is four bytes for RFC 1997 communities and eight bytes for
+    /// extended communities.
+    pub(crate) fn wire_len(&self) -> usize {
+        match self {
+            Self::RFC1997 { .. } => 4,
+            _ => 8
+        }
+    }
+
+    /// This function serializes the community into bytes. RFC 1997 communities are packed into four bytes, all extended communities are
+    /// packed into eight bytes.
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8);
+        match self {
+            Self::RFC1997 { global_administrator, local_administrator } => {
+                bytes.extend_from_slice(&global_administrator.to_be_bytes());
+                bytes.extend_from_slice(&local_administrator.to_be_bytes());
+            },
+            Self::RFC4360ASN { subkind, flags, global_administrator, local_administrator } => {
+                bytes.extend_from_slice(&[flags.bits(), u8::from(*subkind)]);
+                bytes.extend_from_slice(&global_administrator.to_be_bytes());
+                bytes.extend_from_slice(&local_administrator.to_be_bytes());
+            },
+            Self::RFC4360Address { subkind, flags, global_administrator, local_administrator } => {
+                bytes.extend_from_slice(&[flags.bits() | 0x01, u8::from(*subkind)]);
+                bytes.extend_from_slice(&global_administrator.octets());
+                bytes.extend_from_slice(&local_administrator.to_be_bytes());
+            },
+            Self::RFC5668ASN { subkind, flags, global_administrator, local_administrator } => {
+                bytes.extend_from_slice(&[flags.bits() | 0x02, u8::from(*subkind)]);
+                bytes.extend_from_slice(&global_administrator.to_be_bytes());
+                bytes.extend_from_slice(&local_administrator.to_be_bytes());
+            },
+            Self::RFC4360Opaque { subkind, flags, value } => {
+                bytes.extend_from_slice(&[flags.bits() | 0x03, u8::from(*subkind)]);
+                bytes.extend_from_slice(value);
+            },
+            Self::Unknown { kind, subkind, value, .. } => {
+                bytes.extend_from_slice(&[*kind, u8::from(*subkind)]);
+                bytes.extend_from_slice(value);
+            }
+        }
+        bytes
+    }
+}
+
+/// This function returns an iterator lazily reading the communities from the value of a COMMUNITIES path attribute. Unlike the parser of
+/// the path attribute, no list is allocated, so policies checking for a single community (like `NO_EXPORT`) can stop at the first match.
+/// Trailing bytes not forming a complete community are ignored.
+pub fn iter_communities(data: &[u8]) -> impl Iterator<Item = Community> + '_ {
+    data.chunks_exact(4).map(|chunk| Community::RFC1997 {
+        global_administrator: u16::from_be_bytes([chunk[0], chunk[1]]),
+        local_administrator: u16::from_be_bytes([chunk[2], chunk[3]])
+    })
+}
+
+/// This enum represents the route target of an extended community, which identifies the set of sites or VRFs a route should be imported
+/// into. The route target is written as `rt:<ASN or IPv4 address>:<value>` (like `rt:65001:100`), so it can be used in policies without
+/// matching the raw communities.
+///
+/// ## References
+/// - [Route Target Community, Section 4 RFC 4360](https://datatracker.ietf.org/doc/html/rfc4360#section-4)
+/// - [4-Octet AS Specific Extended Community, Section 2 RFC 5668](https://datatracker.ietf.org/doc/html/rfc5668#section-2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum RouteTarget {
+    /// This value indicates a route target assigned by an autonomous system. The value is limited to two bytes for four-octet AS numbers.
+    ASN { asn: u32, value: u32 },
+
+    /// This value indicates a route target assigned by the owner of the IPv4 address
+    Address { address: Ipv4Addr, value: u16 }
+}
+
+impl Display for RouteTarget {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ASN { asn, value } => write!(formatter, "rt:{}:{}", asn, value),
+            Self::Address { address, value } => write!(formatter, "rt:{}:{}", address, value)
+        }
+    }
+}
+
+impl FromStr for RouteTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (administrator, value) = str.strip_prefix("rt:")
+            .and_then(|str| str.rsplit_once(":"))
+            .ok_or(anyhow::anyhow!("Invalid route target syntax"))?;
+        if let Ok(address) = Ipv4Addr::from_str(administrator) {
+            return Ok(Self::Address { address, value: u16::from_str(value)? });
+        }
+
+        let (asn, value) = (u32::from_str(administrator)?, u32::from_str(value)?);
+        if asn > u16::MAX as u32 && value > u16::MAX as u32 {
+            return Err(anyhow::anyhow!("Route target value {} exceeds two bytes for four-octet AS number {}", value, asn));
+        }
+        Ok(Self::ASN { asn, value })
+    }
 }
@@ -0,0 +1,108 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the route flap damping specified in [RFC 2439](https://datatracker.ietf.org/doc/html/rfc2439). Every flap
+//! (withdrawal or re-announcement) of a route increases the penalty of the route's prefix, which decays exponentially over time. Routes
+//! whose penalty exceeds the suppress threshold are hidden until the penalty decayed below the reuse threshold.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::prefix::Prefix;
+
+/// This struct contains the parameters of the route flap damping. The default values are the commonly used values recommended by RIPE.
+///
+/// ## References
+/// - [Configuration Parameters, Section 4.2 RFC 2439](https://datatracker.ietf.org/doc/html/rfc2439#section-4.2)
+/// - [RIPE-580 "RIPE Routing Working Group Recommendations on Route Flap Damping"](https://www.ripe.net/publications/docs/ripe-580)
+#[derive(Clone, Debug, PartialEq, Copy)]
+pub struct DampingParameters {
+    /// The penalty added to the prefix for every flap
+    pub penalty: f64,
+
+    /// The penalty above which the routes of the prefix are suppressed
+    pub suppress_threshold: f64,
+
+    /// The penalty below which the routes of a suppressed prefix are used again
+    pub reuse_threshold: f64,
+
+    /// The time after which the penalty decayed to the half
+    pub half_life: Duration
+}
+
+impl Default for DampingParameters {
+    fn default() -> Self {
+        Self { penalty: 1000.0, suppress_threshold: 6000.0, reuse_threshold: 750.0, half_life: Duration::from_secs(15 * 60) }
+    }
+}
+
+/// This struct represents the damping state of a single prefix with the penalty at the last update of the state.
+#[derive(Clone, Debug, PartialEq, Copy)]
+pub struct DampingState {
+    pub penalty: f64,
+    pub updated: Instant,
+    pub suppressed: bool
+}
+
+/// This struct tracks the penalties of flapping prefixes, so the RIB can hide the routes of damped prefixes.
+#[derive(Clone, Debug)]
+pub struct FlapDamping {
+    parameters: DampingParameters,
+    states: HashMap<Prefix, DampingState>
+}
+
+impl FlapDamping {
+    pub fn new(parameters: DampingParameters) -> Self {
+        Self { parameters, states: HashMap::new() }
+    }
+
+    /// This function records a flap (withdrawal or re-announcement) of the prefix at the specified time. The penalty of the prefix is
+    /// increased and the prefix gets suppressed if the penalty exceeds the suppress threshold.
+    pub fn record_flap(&mut self, prefix: Prefix, now: Instant) {
+        let penalty = self.penalty(&prefix, now);
+        let parameters = self.parameters;
+        let state = self.states.entry(prefix).or_insert(DampingState { penalty, updated: now, suppressed: false });
+        state.suppressed &= penalty >= parameters.reuse_threshold;
+        state.penalty = penalty + parameters.penalty;
+        state.updated = now;
+        state.suppressed |= state.penalty > parameters.suppress_threshold;
+    }
+
+    /// This function returns the penalty of the prefix decayed to the specified time.
+    pub fn penalty(&self, prefix: &Prefix, now: Instant) -> f64 {
+        self.states.get(prefix).map(|state| decay(&self.parameters, state, now)).unwrap_or(0.0)
+    }
+
+    /// This function returns whether the routes of the prefix are suppressed at the specified time. A suppressed prefix stays suppressed
+    /// until its penalty decayed below the reuse threshold.
+    pub fn is_suppressed(&self, prefix: &Prefix, now: Instant) -> bool {
+        self.states.get(prefix)
+            .is_some_and(|state| state.suppressed && decay(&self.parameters, state, now) >= self.parameters.reuse_threshold)
+    }
+
+    /// This function removes the states of all prefixes, whose penalty decayed below the half of the reuse threshold. These prefixes are
+    /// considered stable again, so their history is forgotten.
+    ///
+    /// ## References
+    /// - [Memory Requirements, Section 4.8.1 RFC 2439](https://datatracker.ietf.org/doc/html/rfc2439#section-4.8.1)
+    pub fn prune(&mut self, now: Instant) {
+        let threshold = self.parameters.reuse_threshold / 2.0;
+        self.states.retain(|_, state| decay(&self.parameters, state, now) >= threshold);
+    }
+}
+
+/// This function returns the penalty of the state decayed exponentially from the last update of the state to the specified time.
+fn decay(parameters: &DampingParameters, state: &DampingState, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+    state.penalty * 0.5f64.powf(elapsed / parameters.half_life.as_secs_f64())
+}
@@ -12,40 +12,181 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use nom::bytes::complete::take;
 use nom::IResult;
 use nom::number::complete::{be_u8, be_u32};
+use crate::protocols::bgp::{check_length, parse_element, BGPElement, BgpParseError, HexDump, OpenMessage, PackError};
 use crate::protocols::bgp::rfc4760::MultiprotocolExtensionsCapability;
 use crate::protocols::bgp::rfc6793::FourOctetASNumberSupportCapability;
+use crate::protocols::bgp::rfc9494::LongLivedGracefulRestartCapability;
 
 /// This enum implements a wrapper around [RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392) that defines the capability
 /// advertisement with BGP-4.
-#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Capability {
     MultiprotocolExtensions(MultiprotocolExtensionsCapability),
+
+    /// This value indicates the support for receiving route refresh messages
+    ///
+    /// ## References
+    /// - [Route Refresh Capability, Section 2 RFC 2918](https://datatracker.ietf.org/doc/html/rfc2918#section-2)
+    RouteRefresh,
+
     FourOctetASNumberSupport(FourOctetASNumberSupportCapability),
+
+    /// This value indicates the support for receiving messages longer than 4096 bytes up to
+    /// [MAX_EXTENDED_MESSAGE_LENGTH](crate::protocols::bgp::MAX_EXTENDED_MESSAGE_LENGTH) bytes
+    ///
+    /// ## References
+    /// - [BGP Extended Message Capability, Section 3 RFC 8654](https://datatracker.ietf.org/doc/html/rfc8654#section-3)
+    ExtendedMessage,
+
+    LongLivedGracefulRestart(LongLivedGracefulRestartCapability),
+
+    /// This value indicates a known capability whose length doesn't match the fixed length of the capability
+    Malformed { kind: u8, expected: u8, data: Vec<u8> },
+
     Unknown { kind: u8, data: Vec<u8> }
 }
 
 impl Capability {
+    /// This function creates a capability with the specified code and data, which can be used to advertise capabilities not modeled by
+    /// this implementation (like vendor-specific capabilities). If the data doesn't fit into the one-byte length, this function returns an
+    /// error.
+    pub fn raw(kind: u8, data: Vec<u8>) -> anyhow::Result<Self> {
+        if data.len() > u8::MAX as usize {
+            return Err(anyhow::anyhow!("Capability data is {} bytes long, but only 255 bytes are allowed", data.len()));
+        }
+        Ok(Self::Unknown { kind, data })
+    }
+
     pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
         let (input, kind) = be_u8(input)?;
         let (input, length) = be_u8(input)?;
         let (input, data) = take(length)(input)?;
+        if let Some(expected) = Self::expected_length(kind).filter(|expected| *expected != length) {
+            return Ok((input, Self::Malformed { kind, expected, data: data.to_vec() }));
+        }
+
         Ok((input, match kind {
             1 => Self::MultiprotocolExtensions(MultiprotocolExtensionsCapability::unpack(data)?.1),
+            2 => Self::RouteRefresh,
+            6 => Self::ExtendedMessage,
             65 => Self::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: be_u32(data)?.1 }),
+            71 => Self::LongLivedGracefulRestart(LongLivedGracefulRestartCapability::unpack(data)?.1),
             _ => Self::Unknown { kind, data: data.to_vec() }
         }))
     }
+
+    /// This function serializes the capability. The length is always taken from the encoded data, which must match the fixed length of
+    /// the capabilities known by this implementation (if the capability has a fixed length).
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let (kind, data) = match self {
+            Self::MultiprotocolExtensions(extensions) => (1, extensions.pack()),
+            Self::RouteRefresh => (2, Vec::new()),
+            Self::ExtendedMessage => (6, Vec::new()),
+            Self::FourOctetASNumberSupport(support) => (65, support.as_number.to_be_bytes().to_vec()),
+            Self::LongLivedGracefulRestart(restart) => (71, restart.pack()),
+            Self::Malformed { kind, data, .. } | Self::Unknown { kind, data } => (*kind, data.clone())
+        };
+        debug_assert!(
+            matches!(self, Self::Malformed { .. } | Self::Unknown { .. })
+                || Self::expected_length(kind).is_none_or(|expected| expected as usize == data.len()),
+            "Capability {} encoded with unexpected length {}",
+            kind,
+            data.len()
+        );
+
+        let mut bytes = vec![kind, data.len() as u8];
+        bytes.extend(data);
+        bytes
+    }
+
+    /// This function returns the length of the serialized capability including the code and the length.
+    pub(crate) fn wire_len(&self) -> usize {
+        2 + match self {
+            Self::MultiprotocolExtensions(_) | Self::FourOctetASNumberSupport(_) => 4,
+            Self::RouteRefresh | Self::ExtendedMessage => 0,
+            Self::LongLivedGracefulRestart(restart) => restart.wire_len(),
+            Self::Malformed { data, .. } | Self::Unknown { data, .. } => data.len()
+        }
+    }
+
+    /// This function returns an error, if the capability's data doesn't fit into the one-byte length.
+    pub(crate) fn check_lengths(&self) -> Result<(), PackError> {
+        check_length("Capability", self.pack().len() - 2, u8::MAX as usize)
+    }
+
+    /// This function returns the fixed length of the capability's data for capabilities known by this implementation.
+    fn expected_length(kind: u8) -> Option<u8> {
+        match kind {
+            1 | 65 => Some(4),
+            2 | 6 => Some(0),
+            _ => None
+        }
+    }
+
+    /// This function returns whether the other capability indicates the support of the same feature as this capability. Capabilities
+    /// like the four-octet AS number support match regardless of their values, while multiprotocol extensions only match for the same
+    /// address family.
+    pub(crate) fn matches(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::FourOctetASNumberSupport(_), Self::FourOctetASNumberSupport(_)) => true,
+            (Self::LongLivedGracefulRestart(_), Self::LongLivedGracefulRestart(_)) => true,
+            (Self::Malformed { .. }, _) | (_, Self::Malformed { .. }) => false,
+            _ => self == other
+        }
+    }
+}
+
+impl BGPElement for Capability {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::unpack)
+    }
+}
+
+/// This function returns the capabilities advertised by both the local and the remote router in their open messages. The returned
+/// capabilities are the local ones, so the features supported by both routers can be enabled for the session.
+///
+/// ## References
+/// - [Overview of Operations, Section 3 RFC 3392](https://datatracker.ietf.org/doc/html/rfc3392#section-3)
+pub fn negotiate(local: &OpenMessage, remote: &OpenMessage) -> Vec<Capability> {
+    local.capabilities()
+        .filter(|capability| remote.capabilities().any(|remote_capability| capability.matches(remote_capability)))
+        .cloned()
+        .collect()
+}
+
+impl Debug for Capability {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MultiprotocolExtensions(extensions) => formatter.debug_tuple("MultiprotocolExtensions").field(extensions).finish(),
+            Self::RouteRefresh => formatter.write_str("RouteRefresh"),
+            Self::ExtendedMessage => formatter.write_str("ExtendedMessage"),
+            Self::FourOctetASNumberSupport(support) => formatter.debug_tuple("FourOctetASNumberSupport").field(support).finish(),
+            Self::LongLivedGracefulRestart(restart) => formatter.debug_tuple("LongLivedGracefulRestart").field(restart).finish(),
+            Self::Malformed { kind, expected, data } => formatter.debug_struct("Malformed")
+                .field("kind", kind)
+                .field("expected", expected)
+                .field("data", &HexDump(data))
+                .finish(),
+            Self::Unknown { kind, data } => formatter.debug_struct("Unknown").field("kind", kind).field("data", &HexDump(data)).finish()
+        }
+    }
 }
 
 impl Display for Capability {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::MultiprotocolExtensions(extensions) => write!(formatter, "{}", extensions),
+            Self::RouteRefresh => write!(formatter, "Route refresh"),
+            Self::ExtendedMessage => write!(formatter, "Extended message"),
             Self::FourOctetASNumberSupport(support) => write!(formatter, "AS{}", support.as_number),
+            Self::LongLivedGracefulRestart(restart) => write!(formatter, "{}", restart),
+            Self::Malformed { kind, expected, data } => {
+                write!(formatter, "Malformed {} bytes, expected {} bytes (Kind: {})", data.len(), expected, kind)
+            },
             Self::Unknown { kind, data } => write!(formatter, "Unknown {} bytes (Kind: {})", data.len(), kind)
         }
     }
@@ -0,0 +1,174 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the [RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364) that defines BGP/MPLS IP VPNs. The routes of the
+//! VPNs are advertised with the MPLS-labeled VPN SAFI (128) and are prefixed with a label stack and a route distinguisher, which makes
+//! overlapping address spaces of different VPNs unique in the BGP.
+
+use std::fmt::{Display, Formatter};
+use std::net::Ipv4Addr;
+use nom::bytes::complete::take;
+use nom::error::{Error, ErrorKind};
+use nom::IResult;
+use nom::number::complete::{be_u16, be_u24, be_u32, be_u8};
+use crate::prefix::Prefix;
+use crate::protocols::bgp::{parse_element, BGPElement, BgpParseError};
+use crate::protocols::bgp::rfc4760::AddressFamily;
+
+/// This enum represents the route distinguisher, which is prepended to the addresses of a VPN to make them unique. The route distinguisher
+/// consists of an administrator field and an assigned number field, whose widths are determined by the type of the route distinguisher.
+///
+/// ## References
+/// - [Encoding of Route Distinguishers, Section 4.2 RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364#section-4.2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub enum RouteDistinguisher {
+    /// This value indicates a route distinguisher with a 2-byte ASN as administrator (Type 0)
+    TwoOctetASN { administrator: u16, assigned_number: u32 },
+
+    /// This value indicates a route distinguisher with an IPv4 address as administrator (Type 1)
+    IPv4Address { administrator: Ipv4Addr, assigned_number: u16 },
+
+    /// This value indicates a route distinguisher with a 4-byte ASN as administrator (Type 2)
+    FourOctetASN { administrator: u32, assigned_number: u16 },
+
+    Unknown { kind: u16, value: [u8; 6] }
+}
+
+impl RouteDistinguisher {
+    pub fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, kind) = be_u16(input)?;
+        let (input, value) = take(6usize)(input)?;
+        Ok((input, match kind {
+            0 => Self::TwoOctetASN { administrator: be_u16(value)?.1, assigned_number: be_u32(&value[2..])?.1 },
+            1 => Self::IPv4Address { administrator: Ipv4Addr::from_bits(be_u32(value)?.1), assigned_number: be_u16(&value[4..])?.1 },
+            2 => Self::FourOctetASN { administrator: be_u32(value)?.1, assigned_number: be_u16(&value[4..])?.1 },
+            _ => Self::Unknown { kind, value: value.try_into().unwrap() }
+        }))
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8);
+        match self {
+            Self::TwoOctetASN { administrator, assigned_number } => {
+                bytes.extend_from_slice(&0u16.to_be_bytes());
+                bytes.extend_from_slice(&administrator.to_be_bytes());
+                bytes.extend_from_slice(&assigned_number.to_be_bytes());
+            },
+            Self::IPv4Address { administrator, assigned_number } => {
+                bytes.extend_from_slice(&1u16.to_be_bytes());
+                bytes.extend_from_slice(&administrator.octets());
+                bytes.extend_from_slice(&assigned_number.to_be_bytes());
+            },
+            Self::FourOctetASN { administrator, assigned_number } => {
+                bytes.extend_from_slice(&2u16.to_be_bytes());
+                bytes.extend_from_slice(&administrator.to_be_bytes());
+                bytes.extend_from_slice(&assigned_number.to_be_bytes());
+            },
+            Self::Unknown { kind, value } => {
+                bytes.extend_from_slice(&kind.to_be_bytes());
+                bytes.extend_from_slice(value);
+            }
+        }
+        bytes
+    }
+}
+
+impl BGPElement for RouteDistinguisher {
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), BgpParseError> {
+        parse_element(input, Self::unpack)
+    }
+}
+
+impl Display for RouteDistinguisher {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TwoOctetASN { administrator, assigned_number } => write!(formatter, "{}:{}", administrator, assigned_number),
+            Self::IPv4Address { administrator, assigned_number } => write!(formatter, "{}:{}", administrator, assigned_number),
+            Self::FourOctetASN { administrator, assigned_number } => write!(formatter, "{}:{}", administrator, assigned_number),
+            Self::Unknown { kind, value } => write!(formatter, "Unknown {} (Kind: {})", hex::encode(value), kind)
+        }
+    }
+}
+
+/// This struct represents a VPN prefix advertised in the NLRI of the MPLS-labeled VPN SAFI. The prefix is prefixed with the MPLS label stack
+/// and the route distinguisher of the VPN, which are encoded into the length of the prefix.
+///
+/// ## References
+/// - [BGP Extensions, Section 4.3.4 RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364#section-4.3.4)
+/// - [Carrying Label Mapping Information, Section 2 RFC 8277](https://datatracker.ietf.org/doc/html/rfc8277#section-2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VpnPrefix {
+    pub label_stack: Vec<u32>,
+    pub rd: RouteDistinguisher,
+    pub prefix: Prefix
+}
+
+impl VpnPrefix {
+    /// The label sent in withdrawals instead of the label stack, which is not followed by other labels even though the bottom-of-stack bit
+    /// isn't set.
+    ///
+    /// ## References
+    /// - [Withdrawal of Routes, Section 2.4 RFC 8277](https://datatracker.ietf.org/doc/html/rfc8277#section-2.4)
+    pub const WITHDRAWAL_LABEL: u32 = 0x80000;
+
+    pub(crate) fn unpack(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], Self> {
+        let (input, length) = be_u8(input)?;
+        let (remaining, data) = take(length.div_ceil(8))(input)?;
+
+        let mut label_stack = Vec::new();
+        let mut data = data;
+        loop {
+            let (next, label) = be_u24(data)?;
+            data = next;
+            label_stack.push(label >> 4);
+            if label & 1 == 1 || label >> 4 == Self::WITHDRAWAL_LABEL {
+                break;
+            }
+        }
+
+        let (data, rd) = RouteDistinguisher::unpack(data)?;
+        let prefix_length = (length as usize).checked_sub(label_stack.len() * 24 + 64)
+            .ok_or(nom::Err::Error(Error::new(input, ErrorKind::Verify)))?;
+        let mut prefix = vec![prefix_length as u8];
+        prefix.extend_from_slice(data);
+        let (_, prefix) = Prefix::unpack(&prefix, address_family).map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Verify)))?;
+        Ok((remaining, Self { label_stack, rd, prefix }))
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        self.label_stack.len() * 3 + 8 + self.prefix.wire_len()
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (index, label) in self.label_stack.iter().enumerate() {
+            let bottom_of_stack = index == self.label_stack.len() - 1 && *label != Self::WITHDRAWAL_LABEL;
+            data.extend_from_slice(&((label << 4) | bottom_of_stack as u32).to_be_bytes()[1..]);
+        }
+        data.extend(self.rd.pack());
+
+        let prefix = self.prefix.pack();
+        let mut bytes = vec![(data.len() * 8 + prefix[0] as usize) as u8];
+        bytes.extend(data);
+        bytes.extend_from_slice(&prefix[1..]);
+        bytes
+    }
+}
+
+impl Display for VpnPrefix {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let labels: Vec<String> = self.label_stack.iter().map(u32::to_string).collect();
+        write!(formatter, "{} {} (Labels: {})", self.rd, self.prefix, labels.join(" "))
+    }
+}
@@ -18,12 +18,15 @@
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
 use nom::bytes::complete::take;
+use nom::error::{Error, ErrorKind};
 use nom::IResult;
 use nom::multi::many0;
 use nom::number::complete::{be_u8, be_u16};
 use nom::Parser;
 use crate::prefix::Prefix;
-use crate::protocols::bgp::unpack_address;
+use crate::protocols::bgp::{pack_address, unpack_address};
+use crate::protocols::bgp::rfc4364::VpnPrefix;
+use crate::protocols::bgp::rfc7432::EvpnRoute;
 
 /// This enum represents all AFI (Address family identifier) supported by this BGP implementation, currently we only support IPv4 and IPv6.
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
@@ -34,15 +37,33 @@ pub enum AddressFamily {
     /// This value indicates IPv6 (Internet protocol version 6, 128 bits)
     IPv6,
 
+    /// This value indicates Layer 2 VPN information like EVPN routes
+    ///
+    /// ## References
+    /// - [BGP MPLS-Based Ethernet VPN, Section 7 RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432#section-7)
+    L2VPN,
+
     /// This value indicates an unknown AFI identifier
     Unknown(u16)
 }
 
+impl AddressFamily {
+    /// This function returns the width of addresses of this address family in bytes or none if the address family is unknown.
+    pub fn address_width(&self) -> Option<usize> {
+        match self {
+            Self::IPv4 => Some(4),
+            Self::IPv6 => Some(16),
+            Self::L2VPN | Self::Unknown(_) => None
+        }
+    }
+}
+
 impl From<u16> for AddressFamily {
     fn from(value: u16) -> Self {
         match value {
             0x01 => Self::IPv4,
             0x02 => Self::IPv6,
+            0x19 => Self::L2VPN,
             _ => Self::Unknown(value)
         }
     }
@@ -53,6 +74,7 @@ impl From<AddressFamily> for u16 {
         match value {
             AddressFamily::IPv4 => 0x01,
             AddressFamily::IPv6 => 0x02,
+            AddressFamily::L2VPN => 0x19,
             AddressFamily::Unknown(value) => value
         }
     }
@@ -63,6 +85,7 @@ impl Display for AddressFamily {
         match self {
             Self::IPv4 => write!(formatter, "IPv4"),
             Self::IPv6 => write!(formatter, "IPv6"),
+            Self::L2VPN => write!(formatter, "L2VPN"),
             Self::Unknown(value) => write!(formatter, "Unknown ({})", value)
         }
     }
@@ -70,8 +93,8 @@ impl Display for AddressFamily {
 
 
 
-/// This enum represents all SAFI (Subsequent address family identifier) supported by this BGP implementation, currently we only support
-/// Unicast or Multicast.
+/// This enum represents all SAFI (Subsequent address family identifier) known by this BGP implementation. Besides Unicast and Multicast,
+/// the SAFIs used for labeled unicast, L3VPN and FlowSpec are known.
 ///
 /// ## References
 /// [Subsequent Address Family Identifier, Section 6 RFC 4760](https://datatracker.ietf.org/doc/html/rfc4760#section-6)
@@ -89,6 +112,36 @@ pub enum SubsequentAddressFamily {
     /// [Subsequent Address Family Identifier, Section 6 RFC 4760](https://datatracker.ietf.org/doc/html/rfc4760#section-6)
     Multicast,
 
+    /// This value indicates NLRI with MPLS labels
+    ///
+    /// ## References
+    /// [Carrying Label Mapping Information, Section 2 RFC 8277](https://datatracker.ietf.org/doc/html/rfc8277#section-2)
+    MplsLabels,
+
+    /// This value indicates Ethernet VPN routes
+    ///
+    /// ## References
+    /// [BGP MPLS-Based Ethernet VPN, Section 7 RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432#section-7)
+    Evpn,
+
+    /// This value indicates MPLS-labeled VPN addresses
+    ///
+    /// ## References
+    /// [BGP Extensions, Section 4.3.4 RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364#section-4.3.4)
+    MplsVpn,
+
+    /// This value indicates multicast VPN routes
+    ///
+    /// ## References
+    /// [BGP Encoding, Section 4 RFC 6514](https://datatracker.ietf.org/doc/html/rfc6514#section-4)
+    MulticastVpn,
+
+    /// This value indicates dissemination of flow specification rules
+    ///
+    /// ## References
+    /// [Dissemination of Flow Specification Rules, Section 4 RFC 8955](https://datatracker.ietf.org/doc/html/rfc8955#section-4)
+    FlowSpec,
+
     /// This value indicates an unknown SAFI identifier
     Unknown(u8)
 }
@@ -98,6 +151,11 @@ impl From<u8> for SubsequentAddressFamily {
         match value {
             1 => Self::Unicast,
             2 => Self::Multicast,
+            4 => Self::MplsLabels,
+            70 => Self::Evpn,
+            128 => Self::MplsVpn,
+            129 => Self::MulticastVpn,
+            133 => Self::FlowSpec,
             _ => Self::Unknown(value)
         }
     }
@@ -108,6 +166,11 @@ impl From<SubsequentAddressFamily> for u8 {
         match value {
             SubsequentAddressFamily::Unicast => 1,
             SubsequentAddressFamily::Multicast => 2,
+            SubsequentAddressFamily::MplsLabels => 4,
+            SubsequentAddressFamily::Evpn => 70,
+            SubsequentAddressFamily::MplsVpn => 128,
+            SubsequentAddressFamily::MulticastVpn => 129,
+            SubsequentAddressFamily::FlowSpec => 133,
             SubsequentAddressFamily::Unknown(value) => value
         }
     }
@@ -118,24 +181,159 @@ impl Display for SubsequentAddressFamily {
         match self {
             Self::Unicast => write!(formatter, "Unicast"),
             Self::Multicast => write!(formatter, "Multicast"),
+            Self::MplsLabels => write!(formatter, "MplsLabels"),
+            Self::Evpn => write!(formatter, "Evpn"),
+            Self::MplsVpn => write!(formatter, "MplsVpn"),
+            Self::MulticastVpn => write!(formatter, "MulticastVpn"),
+            Self::FlowSpec => write!(formatter, "FlowSpec"),
             Self::Unknown(value) => write!(formatter, "Unknown ({})", value)
         }
     }
 }
 
+/// This function returns the length of the route distinguisher prepended to every next hop address. For the VPN SAFIs each address of the
+/// next hop is prefixed with a zero route distinguisher, which is only recognized with the `vpn` feature enabled.
+///
+/// ## References
+/// - [BGP Extensions, Section 4.3.2 RFC 4364](https://datatracker.ietf.org/doc/html/rfc4364#section-4.3.2)
+/// - [BGP-MP Extensions, Section 3.2.1.1 RFC 4659](https://datatracker.ietf.org/doc/html/rfc4659#section-3.2.1.1)
+fn route_distinguisher_length(subsequent_address_family: SubsequentAddressFamily) -> usize {
+    match subsequent_address_family {
+        #[cfg(feature = "vpn")]
+        SubsequentAddressFamily::MplsVpn | SubsequentAddressFamily::MulticastVpn => 8,
+        _ => 0
+    }
+}
+
+/// The NLRI of the multiprotocol path attributes, split into prefixes, VPN prefixes and EVPN routes
+#[derive(Default)]
+struct MultiprotocolNlri {
+    prefixes: Vec<Prefix>,
+    vpn_prefixes: Vec<VpnPrefix>,
+    evpn_routes: Vec<EvpnRoute>
+}
+
+/// This function returns the length of the serialized prefixes, VPN prefixes and EVPN routes of the multiprotocol path attributes.
+fn nlri_wire_len(prefixes: &[Prefix], vpn_prefixes: &[VpnPrefix], evpn_routes: &[EvpnRoute]) -> usize {
+    prefixes.iter().map(Prefix::wire_len).sum::<usize>() + vpn_prefixes.iter().map(VpnPrefix::wire_len).sum::<usize>()
+        + evpn_routes.iter().map(EvpnRoute::wire_len).sum::<usize>()
+}
+
+/// This function reads the NLRI of the multiprotocol path attributes. For the MPLS-labeled VPN SAFI, the NLRI are VPN prefixes, which are
+/// only recognized with the `vpn` feature enabled. For the L2VPN AFI with the EVPN SAFI, the NLRI are EVPN routes, which are only
+/// recognized with the `rfc7432` feature enabled.
+fn unpack_nlri(input: &[u8], address_family: AddressFamily, subsequent_address_family: SubsequentAddressFamily)
+    -> IResult<&[u8], MultiprotocolNlri> {
+    if cfg!(feature = "vpn") && subsequent_address_family == SubsequentAddressFamily::MplsVpn {
+        let (input, vpn_prefixes) = many0(|b| VpnPrefix::unpack(b, address_family)).parse(input)?;
+        return Ok((input, MultiprotocolNlri { vpn_prefixes, ..MultiprotocolNlri::default() }));
+    }
+
+    if cfg!(feature = "rfc7432") && (address_family, subsequent_address_family) == (AddressFamily::L2VPN, SubsequentAddressFamily::Evpn) {
+        let (input, evpn_routes) = many0(EvpnRoute::unpack).parse(input)?;
+        return Ok((input, MultiprotocolNlri { evpn_routes, ..MultiprotocolNlri::default() }));
+    }
+
+    let (input, prefixes) = many0(|b| Prefix::unpack(b, address_family)).parse(input)?;
+    Ok((input, MultiprotocolNlri { prefixes, ..MultiprotocolNlri::default() }))
+}
+
+/// This function returns the address family of the next hop addresses. For the L2VPN AFI, the next hop is an IPv4 or IPv6 address, which is
+/// determined by the length of the next hop.
+fn next_hop_address_family(address_family: AddressFamily, length: u8) -> AddressFamily {
+    match address_family {
+        AddressFamily::L2VPN if length.is_multiple_of(16) => AddressFamily::IPv6,
+        AddressFamily::L2VPN => AddressFamily::IPv4,
+        _ => address_family
+    }
+}
+
+/// This function returns whether the length of the next hop matches the address family of the next hop and the SAFI. The next hop consists
+/// of one or two addresses (like the global and link-local IPv6 address), which are prefixed with a route distinguisher for VPN SAFIs.
+/// FlowSpec routes can also be advertised without a next hop.
+///
+/// ## References
+/// - [Constructing the Next Hop Field, Section 3 RFC 2545](https://datatracker.ietf.org/doc/html/rfc2545#section-3)
+/// - [Dissemination of Flow Specification Rules, Section 4 RFC 8955](https://datatracker.ietf.org/doc/html/rfc8955#section-4)
+fn valid_next_hop_length(address_family: AddressFamily, subsequent_address_family: SubsequentAddressFamily, length: u8) -> bool {
+    let Some(width) = address_family.address_width() else {
+        return true;
+    };
+
+    let length = length as usize;
+    let single = route_distinguisher_length(subsequent_address_family) + width;
+    length == single || length == single * 2 || (length == 0 && subsequent_address_family == SubsequentAddressFamily::FlowSpec)
+}
+
+/// This struct represents the next hop of the multiprotocol reachable path attribute. The next hop is always encoded with a length prefix
+/// and can contain a link-local address after the global address (used for IPv6). For VPN SAFIs, the route distinguisher in front of the
+/// addresses is stripped, so only the underlying addresses are exposed.
+///
+/// ## References
+/// - [Multiprotocol Reachable NLRI - MP_REACH_NLRI, Section 3 RFC 4760](https://datatracker.ietf.org/doc/html/rfc4760#section-3)
+/// - [Constructing the Next Hop Field, Section 3 RFC 2545](https://datatracker.ietf.org/doc/html/rfc2545#section-3)
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
 pub struct MultiprotocolNextHop {
-    address: IpAddr,
-    link_local_address: IpAddr
+    pub address: IpAddr,
+    pub link_local_address: Option<IpAddr>
 }
 
 impl MultiprotocolNextHop {
-    fn unpack(input: &[u8], address_family: AddressFamily) -> IResult<&[u8], Self> {
+    /// This function returns this next hop with an IPv4-mapped IPv6 address (`::ffff:a.b.c.d`) converted back to the IPv4 address. Some
+    /// peers send IPv4 next hops in this form, which must not be treated as an IPv6 next hop when installing the routes.
+    pub fn normalized(&self) -> Self {
+        let address = match self.address {
+            IpAddr::V6(address) => address.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(self.address),
+            address => address
+        };
+        Self { address, link_local_address: self.link_local_address }
+    }
+
+    /// This function reads the length-prefixed next hop. If the next hop is absent (a length of zero, only allowed for FlowSpec), this
+    /// function returns none, so the next hop isn't mistaken for the unspecified address.
+    fn unpack(input: &[u8], address_family: AddressFamily, subsequent_address_family: SubsequentAddressFamily)
+        -> IResult<&[u8], Option<Self>> {
         let (input, length) = be_u8(input)?;
         let (input, data) = take(length)(input)?;
-        let (data, address) = unpack_address(data, address_family)?;
-        let (_, link_local_address) = unpack_address(data, address_family)?;
-        Ok((input, Self { address, link_local_address }))
+
+        let route_distinguisher_length = route_distinguisher_length(subsequent_address_family);
+        let address_family = next_hop_address_family(address_family, length);
+        if !valid_next_hop_length(address_family, subsequent_address_family, length) {
+            return Err(nom::Err::Error(Error::new(data, ErrorKind::LengthValue)));
+        }
+
+        if length == 0 {
+            return Ok((input, None));
+        }
+        let unpack_next_hop_address = |data| {
+            let (data, _) = take(route_distinguisher_length)(data)?;
+            unpack_address(data, address_family)
+        };
+
+        let (data, address) = unpack_next_hop_address(data)?;
+        let link_local_address = if data.is_empty() { None } else { Some(unpack_next_hop_address(data)?.1) };
+        Ok((input, Some(Self { address, link_local_address })))
+    }
+
+    fn wire_len(&self, subsequent_address_family: SubsequentAddressFamily) -> usize {
+        let address_length = |address: &IpAddr| if address.is_ipv4() { 4 } else { 16 };
+        let route_distinguisher_length = route_distinguisher_length(subsequent_address_family);
+        1 + route_distinguisher_length + address_length(&self.address)
+            + self.link_local_address.as_ref().map(|address| route_distinguisher_length + address_length(address)).unwrap_or(0)
+    }
+
+    fn pack(&self, subsequent_address_family: SubsequentAddressFamily) -> Vec<u8> {
+        let route_distinguisher = vec![0; route_distinguisher_length(subsequent_address_family)];
+        let mut data = route_distinguisher.clone();
+        data.extend(pack_address(&self.address));
+        if let Some(link_local_address) = &self.link_local_address {
+            data.extend(&route_distinguisher);
+            data.extend(pack_address(link_local_address));
+        }
+
+        let mut bytes = vec![data.len() as u8];
+        bytes.extend(data);
+        bytes
     }
 }
 
@@ -163,6 +361,12 @@ impl MultiprotocolExtensionsCapability {
             subsequent_address_family: SubsequentAddressFamily::from(subsequent_address_family)
         }))
     }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = u16::from(self.address_family).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[0, u8::from(self.subsequent_address_family)]);
+        bytes
+    }
 }
 
 /// This struct represents the multiprotocol reachable path attribute defined by the Multiprotocol Extensions for BGP as an optional and
@@ -175,8 +379,11 @@ impl MultiprotocolExtensionsCapability {
 pub struct MultiprotocolReachablePathAttribute {
     pub address_family: AddressFamily,
     pub subsequent_address_family: SubsequentAddressFamily,
-    pub next_hop_address: MultiprotocolNextHop,
-    pub network_layer_reachability_information: Vec<Prefix>
+    /// The next hop of the routes, which is absent for FlowSpec routes advertised without a next hop
+    pub next_hop_address: Option<MultiprotocolNextHop>,
+    pub network_layer_reachability_information: Vec<Prefix>,
+    pub vpn_network_layer_reachability_information: Vec<VpnPrefix>,
+    pub evpn_network_layer_reachability_information: Vec<EvpnRoute>
 }
 
 impl MultiprotocolReachablePathAttribute {
@@ -187,17 +394,49 @@ impl MultiprotocolReachablePathAttribute {
         let (input, subsequent_address_family) = be_u8(input)?;
         let subsequent_address_family = SubsequentAddressFamily::from(subsequent_address_family);
 
-        let (input, next_hop_address) = MultiprotocolNextHop::unpack(input, address_family)?;
+        let (input, next_hop_address) = MultiprotocolNextHop::unpack(input, address_family, subsequent_address_family)?;
         let (nlri, _) = be_u8(input)?;
 
-        let (_, network_layer_reachability_information) = many0(|b| Prefix::unpack(b, address_family)).parse(nlri)?;
+        let (_, nlri) = unpack_nlri(nlri, address_family, subsequent_address_family)?;
         Ok((&[], Self {
             address_family,
             subsequent_address_family,
             next_hop_address,
-            network_layer_reachability_information
+            network_layer_reachability_information: nlri.prefixes,
+            vpn_network_layer_reachability_information: nlri.vpn_prefixes,
+            evpn_network_layer_reachability_information: nlri.evpn_routes
         }))
     }
+
+    /// This function returns the count of routes in the NLRI of this path attribute, which includes the prefixes, the VPN prefixes and the
+    /// EVPN routes.
+    pub fn route_count(&self) -> usize {
+        self.network_layer_reachability_information.len() + self.vpn_network_layer_reachability_information.len()
+            + self.evpn_network_layer_reachability_information.len()
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        let next_hop_length = self.next_hop_address.map_or(1, |next_hop| next_hop.wire_len(self.subsequent_address_family));
+        4 + next_hop_length + nlri_wire_len(
+            &self.network_layer_reachability_information,
+            &self.vpn_network_layer_reachability_information,
+            &self.evpn_network_layer_reachability_information
+        )
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = u16::from(self.address_family).to_be_bytes().to_vec();
+        bytes.push(u8::from(self.subsequent_address_family));
+        match &self.next_hop_address {
+            Some(next_hop_address) => bytes.extend(next_hop_address.pack(self.subsequent_address_family)),
+            None => bytes.push(0)
+        }
+        bytes.push(0);
+        bytes.extend(self.network_layer_reachability_information.iter().flat_map(Prefix::pack));
+        bytes.extend(self.vpn_network_layer_reachability_information.iter().flat_map(VpnPrefix::pack));
+        bytes.extend(self.evpn_network_layer_reachability_information.iter().flat_map(EvpnRoute::pack));
+        bytes
+    }
 }
 
 /// This struct represents the multiprotocol unreachable NLRI path attribute defined by the Multiprotocol Extensions for BGP as an optional
@@ -209,7 +448,9 @@ impl MultiprotocolReachablePathAttribute {
 pub struct MultiprotocolUnreachablePathAttribute {
     pub address_family: AddressFamily,
     pub subsequent_address_family: SubsequentAddressFamily,
-    pub network_layer_reachability_information: Vec<Prefix>
+    pub network_layer_reachability_information: Vec<Prefix>,
+    pub vpn_network_layer_reachability_information: Vec<VpnPrefix>,
+    pub evpn_network_layer_reachability_information: Vec<EvpnRoute>
 }
 
 impl MultiprotocolUnreachablePathAttribute {
@@ -218,11 +459,37 @@ impl MultiprotocolUnreachablePathAttribute {
         let (nlri, subsequent_address_family) = be_u8(input)?;
         let address_family = AddressFamily::from(address_family);
         let subsequent_address_family = SubsequentAddressFamily::from(subsequent_address_family);
-        let (_, network_layer_reachability_information) = many0(|b| Prefix::unpack(b, address_family)).parse(nlri)?;
+        let (_, nlri) = unpack_nlri(nlri, address_family, subsequent_address_family)?;
         Ok((&[], Self {
             address_family,
             subsequent_address_family,
-            network_layer_reachability_information
+            network_layer_reachability_information: nlri.prefixes,
+            vpn_network_layer_reachability_information: nlri.vpn_prefixes,
+            evpn_network_layer_reachability_information: nlri.evpn_routes
         }))
     }
+
+    /// This function returns the count of routes withdrawn by this path attribute, which includes the prefixes, the VPN prefixes and the
+    /// EVPN routes.
+    pub fn route_count(&self) -> usize {
+        self.network_layer_reachability_information.len() + self.vpn_network_layer_reachability_information.len()
+            + self.evpn_network_layer_reachability_information.len()
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        3 + nlri_wire_len(
+            &self.network_layer_reachability_information,
+            &self.vpn_network_layer_reachability_information,
+            &self.evpn_network_layer_reachability_information
+        )
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = u16::from(self.address_family).to_be_bytes().to_vec();
+        bytes.push(u8::from(self.subsequent_address_family));
+        bytes.extend(self.network_layer_reachability_information.iter().flat_map(Prefix::pack));
+        bytes.extend(self.vpn_network_layer_reachability_information.iter().flat_map(VpnPrefix::pack));
+        bytes.extend(self.evpn_network_layer_reachability_information.iter().flat_map(EvpnRoute::pack));
+        bytes
+    }
 }
@@ -15,6 +15,40 @@
 //! This module is implementing RFC 6793 which adds support for 4-byte AS numbers to the BGP implementation. This is done by sending a
 //! capability in the handshake.
 
+use crate::protocols::bgp::{PathAttribute, PathAttributeFlags};
+use crate::protocols::bgp::path_attr::{all_asns, ASPathSegment};
+
+/// The AS number sent instead of an AS number not fitting into two bytes to peers without support for four-octet AS numbers, like in the
+/// open message and the AS_PATH path attribute
+///
+/// ## References
+/// - [Protocol Extensions, Section 4 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4)
+pub const AS_TRANS: u16 = 23456;
+
+/// The type code of the AS4_PATH path attribute, which carries the AS path with four-octet AS numbers to peers without support for them
+///
+/// ## References
+/// - [Protocol Extensions, Section 4 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4)
+pub const AS4_PATH: u8 = 17;
+
+/// This function returns the AS4_PATH path attribute, which is sent alongside the AS_PATH path attribute to peers without support for
+/// four-octet AS numbers. The attribute is only needed if an AS number of the path doesn't fit into two bytes and was replaced by
+/// [AS_TRANS] in the AS_PATH. Following to RFC 6793, the confederation segments aren't included.
+///
+/// ## References
+/// - [Interaction with NEW BGP Speakers, Section 4.2.2 RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4.2.2)
+pub fn as4_path(segments: &[ASPathSegment]) -> Option<PathAttribute> {
+    if all_asns(segments).all(|asn| asn <= u16::MAX as u32) {
+        return None;
+    }
+
+    let data = segments.iter()
+        .filter(|segment| matches!(segment, ASPathSegment::Set(_) | ASPathSegment::Sequence(_)))
+        .flat_map(|segment| segment.pack_with(true))
+        .collect();
+    Some(PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL | PathAttributeFlags::TRANSITIVE, kind: AS4_PATH, data })
+}
+
 /// This struct represents the 4-byte AS number support of the router. It indicates the support for 4-byte ASN numbers of the router and
 /// contains the uncut AS number announced by this implementation.
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
@@ -0,0 +1,250 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the [RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432) that defines BGP MPLS-Based Ethernet VPNs (EVPN)
+//! and the IP Prefix route of [RFC 9136](https://datatracker.ietf.org/doc/html/rfc9136). The EVPN routes are advertised with the L2VPN AFI
+//! (25) and the EVPN SAFI (70). Currently only the MAC/IP Advertisement and IP Prefix routes are decoded, the other route types are
+//! preserved as opaque bytes.
+
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use nom::bytes::complete::take;
+use nom::combinator::verify;
+use nom::error::{Error, ErrorKind};
+use nom::{IResult, Parser};
+use nom::number::complete::{be_u24, be_u32, be_u8};
+use crate::prefix::Prefix;
+use crate::protocols::bgp::{check_length, pack_address, unpack_address, PackError};
+use crate::protocols::bgp::rfc4364::RouteDistinguisher;
+use crate::protocols::bgp::rfc4760::AddressFamily;
+
+/// This function reads an IP address prefixed with its length in bits, which is zero if no address is present.
+fn unpack_optional_address(input: &[u8]) -> IResult<&[u8], Option<IpAddr>> {
+    let (input, length) = be_u8(input)?;
+    match length {
+        0 => Ok((input, None)),
+        32 => unpack_address(input, AddressFamily::IPv4).map(|(input, address)| (input, Some(address))),
+        128 => unpack_address(input, AddressFamily::IPv6).map(|(input, address)| (input, Some(address))),
+        _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)))
+    }
+}
+
+/// This struct represents the MAC/IP Advertisement route, which advertises the MAC address and optionally the IP address of a host in the
+/// Ethernet VPN.
+///
+/// ## References
+/// - [MAC/IP Advertisement Route, Section 7.2 RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432#section-7.2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct MacIpAdvertisementRoute {
+    pub rd: RouteDistinguisher,
+    pub ethernet_segment_identifier: [u8; 10],
+    pub ethernet_tag: u32,
+    pub mac_address: [u8; 6],
+    pub ip_address: Option<IpAddr>,
+    pub mpls_label1: u32,
+    pub mpls_label2: Option<u32>
+}
+
+impl MacIpAdvertisementRoute {
+    fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, rd) = RouteDistinguisher::unpack(input)?;
+        let (input, ethernet_segment_identifier) = take(10usize)(input)?;
+        let (input, ethernet_tag) = be_u32(input)?;
+        let (input, _) = verify(be_u8, |length| *length == 48).parse(input)?;
+        let (input, mac_address) = take(6usize)(input)?;
+        let (input, ip_address) = unpack_optional_address(input)?;
+        let (input, mpls_label1) = be_u24(input)?;
+        let (input, mpls_label2) = if input.is_empty() { (input, None) } else { be_u24(input).map(|(input, label)| (input, Some(label)))? };
+        Ok((input, Self {
+            rd,
+            ethernet_segment_identifier: ethernet_segment_identifier.try_into().unwrap(),
+            ethernet_tag,
+            mac_address: mac_address.try_into().unwrap(),
+            ip_address,
+            mpls_label1,
+            mpls_label2
+        }))
+    }
+
+    fn wire_len(&self) -> usize {
+        let address_length = self.ip_address.map(|address| if address.is_ipv4() { 4 } else { 16 }).unwrap_or(0);
+        33 + address_length + if self.mpls_label2.is_some() { 3 } else { 0 }
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        let mut bytes = self.rd.pack();
+        bytes.extend_from_slice(&self.ethernet_segment_identifier);
+        bytes.extend_from_slice(&self.ethernet_tag.to_be_bytes());
+        bytes.push(48);
+        bytes.extend_from_slice(&self.mac_address);
+        match &self.ip_address {
+            Some(address) => {
+                bytes.push(if address.is_ipv4() { 32 } else { 128 });
+                bytes.extend(pack_address(address));
+            },
+            None => bytes.push(0)
+        }
+        bytes.extend_from_slice(&self.mpls_label1.to_be_bytes()[1..]);
+        if let Some(mpls_label2) = self.mpls_label2 {
+            bytes.extend_from_slice(&mpls_label2.to_be_bytes()[1..]);
+        }
+        bytes
+    }
+}
+
+/// This struct represents the IP Prefix route, which advertises an IP prefix reachable through the Ethernet VPN. The address family of the
+/// prefix and the gateway address is determined by the length of the route.
+///
+/// ## References
+/// - [The BGP EVPN IP Prefix Route, Section 3 RFC 9136](https://datatracker.ietf.org/doc/html/rfc9136#section-3)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IpPrefixRoute {
+    pub rd: RouteDistinguisher,
+    pub ethernet_segment_identifier: [u8; 10],
+    pub ethernet_tag: u32,
+    pub prefix: Prefix,
+    pub gateway: IpAddr,
+    pub mpls_label: u32
+}
+
+impl IpPrefixRoute {
+    fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let address_family = match input.len() {
+            34 => AddressFamily::IPv4,
+            58 => AddressFamily::IPv6,
+            _ => return Err(nom::Err::Error(Error::new(input, ErrorKind::LengthValue)))
+        };
+
+        let (input, rd) = RouteDistinguisher::unpack(input)?;
+        let (input, ethernet_segment_identifier) = take(10usize)(input)?;
+        let (input, ethernet_tag) = be_u32(input)?;
+        let (prefix_input, mask) = be_u8(input)?;
+        let (input, address) = unpack_address(prefix_input, address_family)?;
+        let prefix = Prefix::new_canonical(address, mask).map_err(|_| nom::Err::Error(Error::new(prefix_input, ErrorKind::Verify)))?;
+        let (input, gateway) = unpack_address(input, address_family)?;
+        let (input, mpls_label) = be_u24(input)?;
+        Ok((input, Self {
+            rd,
+            ethernet_segment_identifier: ethernet_segment_identifier.try_into().unwrap(),
+            ethernet_tag,
+            prefix,
+            gateway,
+            mpls_label
+        }))
+    }
+
+    fn wire_len(&self) -> usize {
+        if self.prefix.family() == AddressFamily::IPv4 { 34 } else { 58 }
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        let mut bytes = self.rd.pack();
+        bytes.extend_from_slice(&self.ethernet_segment_identifier);
+        bytes.extend_from_slice(&self.ethernet_tag.to_be_bytes());
+        bytes.push(self.prefix.mask());
+        bytes.extend(pack_address(&self.prefix.network()));
+        bytes.extend(pack_address(&self.gateway));
+        bytes.extend_from_slice(&self.mpls_label.to_be_bytes()[1..]);
+        bytes
+    }
+}
+
+/// This enum represents a route advertised in the NLRI of the EVPN SAFI. Every route is prefixed with the route type and the length of the
+/// route type specific data.
+///
+/// ## References
+/// - [BGP EVPN Routes, Section 7 RFC 7432](https://datatracker.ietf.org/doc/html/rfc7432#section-7)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EvpnRoute {
+    /// This value indicates an Ethernet Auto-Discovery route (Type 1), which isn't decoded by this implementation
+    EthernetAutoDiscovery(Vec<u8>),
+
+    /// This value indicates a MAC/IP Advertisement route (Type 2)
+    MacIpAdvertisement(MacIpAdvertisementRoute),
+
+    /// This value indicates an Inclusive Multicast Ethernet Tag route (Type 3), which isn't decoded by this implementation
+    InclusiveMulticast(Vec<u8>),
+
+    /// This value indicates an Ethernet Segment route (Type 4), which isn't decoded by this implementation
+    EthernetSegment(Vec<u8>),
+
+    /// This value indicates an IP Prefix route (Type 5)
+    IpPrefix(IpPrefixRoute),
+
+    Unknown { kind: u8, data: Vec<u8> }
+}
+
+impl EvpnRoute {
+    pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, kind) = be_u8(input)?;
+        let (input, length) = be_u8(input)?;
+        let (input, data) = take(length)(input)?;
+        Ok((input, match kind {
+            1 => Self::EthernetAutoDiscovery(data.to_vec()),
+            2 => Self::MacIpAdvertisement(MacIpAdvertisementRoute::unpack(data)?.1),
+            3 => Self::InclusiveMulticast(data.to_vec()),
+            4 => Self::EthernetSegment(data.to_vec()),
+            5 => Self::IpPrefix(IpPrefixRoute::unpack(data)?.1),
+            _ => Self::Unknown { kind, data: data.to_vec() }
+        }))
+    }
+
+    /// This function returns an error, if the route's data doesn't fit into the one-byte length.
+    pub(crate) fn check_lengths(&self) -> Result<(), PackError> {
+        check_length("EVPN route", self.pack().len() - 2, u8::MAX as usize)
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        2 + match self {
+            Self::EthernetAutoDiscovery(data) | Self::InclusiveMulticast(data) | Self::EthernetSegment(data) => data.len(),
+            Self::MacIpAdvertisement(route) => route.wire_len(),
+            Self::IpPrefix(route) => route.wire_len(),
+            Self::Unknown { data, .. } => data.len()
+        }
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let (kind, data) = match self {
+            Self::EthernetAutoDiscovery(data) => (1, data.clone()),
+            Self::MacIpAdvertisement(route) => (2, route.pack()),
+            Self::InclusiveMulticast(data) => (3, data.clone()),
+            Self::EthernetSegment(data) => (4, data.clone()),
+            Self::IpPrefix(route) => (5, route.pack()),
+            Self::Unknown { kind, data } => (*kind, data.clone())
+        };
+
+        let mut bytes = vec![kind, data.len() as u8];
+        bytes.extend(data);
+        bytes
+    }
+}
+
+impl Display for EvpnRoute {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EthernetAutoDiscovery(data) => write!(formatter, "Ethernet auto-discovery {} bytes", data.len()),
+            Self::MacIpAdvertisement(route) => {
+                let mac_address: Vec<String> = route.mac_address.iter().map(|byte| format!("{:02x}", byte)).collect();
+                match &route.ip_address {
+                    Some(address) => write!(formatter, "MAC/IP {} {} {}", route.rd, mac_address.join(":"), address),
+                    None => write!(formatter, "MAC/IP {} {}", route.rd, mac_address.join(":"))
+                }
+            },
+            Self::InclusiveMulticast(data) => write!(formatter, "Inclusive multicast {} bytes", data.len()),
+            Self::EthernetSegment(data) => write!(formatter, "Ethernet segment {} bytes", data.len()),
+            Self::IpPrefix(route) => write!(formatter, "IP prefix {} {} via {}", route.rd, route.prefix, route.gateway),
+            Self::Unknown { kind, data } => write!(formatter, "Unknown {} bytes (Kind: {})", data.len(), kind)
+        }
+    }
+}
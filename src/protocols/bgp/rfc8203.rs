@@ -0,0 +1,85 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the [RFC 8203](https://datatracker.ietf.org/doc/html/rfc8203) that allows attaching a short UTF-8 message to
+//! the Cease NOTIFICATION sent when shutting down or resetting a session. This message is used by operators to inform the peer's operator
+//! about the reason of the shutdown (like maintenance work).
+
+use nom::bytes::complete::take;
+use nom::error::{Error, ErrorKind};
+use nom::IResult;
+use nom::number::complete::be_u8;
+use crate::protocols::bgp::{CeaseSubcode, NotificationMessage};
+
+/// This struct represents the shutdown communication sent in the data of a Cease NOTIFICATION with the Administrative Shutdown or the
+/// Administrative Reset subcode. The message is limited to 255 bytes of UTF-8 encoded text.
+///
+/// ## References
+/// - [Shutdown Communication, Section 2 RFC 8203](https://datatracker.ietf.org/doc/html/rfc8203#section-2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ShutdownCommunication {
+    message: String
+}
+
+impl ShutdownCommunication {
+    /// This function creates a shutdown communication with the specified message. If the message doesn't fit into the 255 bytes allowed,
+    /// this function returns an error.
+    pub fn new(message: impl Into<String>) -> anyhow::Result<Self> {
+        let message = message.into();
+        if message.len() > u8::MAX as usize {
+            return Err(anyhow::anyhow!("Shutdown communication is {} bytes long, but only 255 bytes are allowed", message.len()));
+        }
+        Ok(Self { message })
+    }
+
+    #[inline(always)]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, length) = be_u8(input)?;
+        let (input, message) = take(length)(input)?;
+        let message = String::from_utf8(message.to_vec()).map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Verify)))?;
+        Ok((input, Self { message }))
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut bytes = vec![self.message.len() as u8];
+        bytes.extend_from_slice(self.message.as_bytes());
+        bytes
+    }
+}
+
+impl NotificationMessage {
+    /// This function creates a Cease NOTIFICATION with the Administrative Shutdown subcode (2) carrying the specified shutdown
+    /// communication.
+    pub fn administrative_shutdown(communication: &ShutdownCommunication) -> Self {
+        Self { data: communication.pack(), ..Self::cease(CeaseSubcode::AdministrativeShutdown) }
+    }
+
+    /// This function creates a Cease NOTIFICATION with the Administrative Reset subcode (4) carrying the specified shutdown communication.
+    pub fn administrative_reset(communication: &ShutdownCommunication) -> Self {
+        Self { data: communication.pack(), ..Self::cease(CeaseSubcode::AdministrativeReset) }
+    }
+
+    /// This function returns the shutdown communication of this notification, if this notification is a Cease NOTIFICATION with the
+    /// Administrative Shutdown or Administrative Reset subcode and carries a valid shutdown communication.
+    pub fn shutdown_communication(&self) -> Option<String> {
+        if self.error_code != 6 || !matches!(self.error_subcode, 2 | 4) {
+            return None;
+        }
+        ShutdownCommunication::unpack(&self.data).ok().map(|(_, communication)| communication.message)
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the [RFC 9012](https://datatracker.ietf.org/doc/html/rfc9012) that defines the Tunnel Encapsulation attribute.
+//! The attribute tells the receiver how to encapsulate the traffic for the routes (like VXLAN or SRv6 tunnels). Only the outer TLVs are
+//! decoded by this implementation, the sub-TLVs describing the tunnel are preserved opaquely.
+
+use nom::bytes::complete::take;
+use nom::combinator::all_consuming;
+use nom::IResult;
+use nom::multi::many0;
+use nom::number::complete::{be_u16, be_u8};
+use nom::Parser;
+use crate::protocols::bgp::{check_length, PackError};
+
+/// This struct represents a single tunnel of the Tunnel Encapsulation attribute with the type of the tunnel and the sub-TLVs describing
+/// the tunnel. The sub-TLVs with a type below 128 have a one-byte length, while all other sub-TLVs have a two-byte length.
+///
+/// ## References
+/// - [The Tunnel Encapsulation Attribute, Section 2 RFC 9012](https://datatracker.ietf.org/doc/html/rfc9012#section-2)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct TunnelTlv {
+    pub tunnel_type: u16,
+    pub sub_tlvs: Vec<(u8, Vec<u8>)>
+}
+
+impl TunnelTlv {
+    pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, tunnel_type) = be_u16(input)?;
+        let (input, length) = be_u16(input)?;
+        let (input, value) = take(length)(input)?;
+        let (_, sub_tlvs) = all_consuming(many0(Self::unpack_sub_tlv)).parse(value)?;
+        Ok((input, Self { tunnel_type, sub_tlvs }))
+    }
+
+    fn unpack_sub_tlv(input: &[u8]) -> IResult<&[u8], (u8, Vec<u8>)> {
+        let (input, kind) = be_u8(input)?;
+        let (input, length) = if kind < 128 {
+            let (input, length) = be_u8(input)?;
+            (input, length as u16)
+        } else { be_u16(input)? };
+        let (input, value) = take(length)(input)?;
+        Ok((input, (kind, value.to_vec())))
+    }
+
+    /// This function returns an error, if the data of a sub-TLV or the value of the TLV doesn't fit into its length field. The sub-TLVs
+    /// with a type code below 128 have a one-byte length.
+    pub(crate) fn check_lengths(&self) -> Result<(), PackError> {
+        for (kind, data) in &self.sub_tlvs {
+            check_length("Tunnel sub-TLV", data.len(), if *kind < 128 { u8::MAX as usize } else { u16::MAX as usize })?;
+        }
+        check_length("Tunnel TLV", self.pack().len() - 4, u16::MAX as usize)
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        4 + self.sub_tlvs.iter().map(|(kind, data)| if *kind < 128 { 2 + data.len() } else { 3 + data.len() }).sum::<usize>()
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut value = Vec::new();
+        for (kind, data) in &self.sub_tlvs {
+            value.push(*kind);
+            if *kind < 128 {
+                value.push(data.len() as u8);
+            } else {
+                value.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            }
+            value.extend_from_slice(data);
+        }
+
+        let mut bytes = self.tunnel_type.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        bytes.extend(value);
+        bytes
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! This module implements the [RFC 9494](https://datatracker.ietf.org/doc/html/rfc9494) that defines the Long-Lived Graceful Restart
+//! (LLGR) for BGP. LLGR extends the graceful restart with a much longer stale time, while the retained routes are marked with the
+//! `LLGR_STALE` community ([Community::LLGR_STALE](crate::protocols::bgp::rfc1997::Community::LLGR_STALE)) and depreferenced.
+
+use std::fmt::{Display, Formatter};
+use nom::IResult;
+use nom::combinator::all_consuming;
+use nom::multi::many0;
+use nom::number::complete::{be_u16, be_u24, be_u8};
+use nom::Parser;
+use crate::protocols::bgp::rfc4760::{AddressFamily, SubsequentAddressFamily};
+
+/// This struct represents the LLGR parameters of a single address family, which contain the flags and the time in seconds for which the
+/// stale routes of the address family are retained after the session was lost.
+///
+/// ## References
+/// - [Long-Lived Graceful Restart Capability, Section 3 RFC 9494](https://datatracker.ietf.org/doc/html/rfc9494#section-3)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy)]
+pub struct LongLivedGracefulRestartFamily {
+    pub address_family: AddressFamily,
+    pub subsequent_address_family: SubsequentAddressFamily,
+    pub flags: u8,
+
+    /// The stale time in seconds, which is encoded with three bytes
+    pub stale_time: u32
+}
+
+impl LongLivedGracefulRestartFamily {
+    /// The flag indicating that the forwarding state of the address family was preserved during the restart
+    pub const FORWARDING_STATE: u8 = 0b1000_0000;
+
+    fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, address_family) = be_u16(input)?;
+        let (input, subsequent_address_family) = be_u8(input)?;
+        let (input, flags) = be_u8(input)?;
+        let (input, stale_time) = be_u24(input)?;
+        Ok((input, Self {
+            address_family: AddressFamily::from(address_family),
+            subsequent_address_family: SubsequentAddressFamily::from(subsequent_address_family),
+            flags,
+            stale_time
+        }))
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        let mut bytes = u16::from(self.address_family).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[u8::from(self.subsequent_address_family), self.flags]);
+        bytes.extend_from_slice(&self.stale_time.to_be_bytes()[1..]);
+        bytes
+    }
+}
+
+/// This struct represents the capability parameter for the open message that indicates the support of the Long-Lived Graceful Restart
+/// with the parameters of every address family, for which the stale routes are retained.
+///
+/// ## References
+/// - [Long-Lived Graceful Restart Capability, Section 3 RFC 9494](https://datatracker.ietf.org/doc/html/rfc9494#section-3)
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct LongLivedGracefulRestartCapability {
+    pub address_families: Vec<LongLivedGracefulRestartFamily>
+}
+
+impl LongLivedGracefulRestartCapability {
+    pub(crate) fn unpack(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, address_families) = all_consuming(many0(LongLivedGracefulRestartFamily::unpack)).parse(input)?;
+        Ok((input, Self { address_families }))
+    }
+
+    pub(crate) fn wire_len(&self) -> usize {
+        self.address_families.len() * 7
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        self.address_families.iter().flat_map(LongLivedGracefulRestartFamily::pack).collect()
+    }
+}
+
+impl Display for LongLivedGracefulRestartCapability {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let families: Vec<String> = self.address_families.iter()
+            .map(|family| format!("{} ({}) {}s", family.address_family, family.subsequent_address_family, family.stale_time))
+            .collect();
+        write!(formatter, "Long-lived graceful restart for {}", families.join(", "))
+    }
+}
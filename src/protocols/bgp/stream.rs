@@ -0,0 +1,61 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the reading and writing of BGP messages from and to asynchronous Tokio streams like a `TcpStream`. Every
+//! message is read completely (header and body) before being decoded.
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::protocols::bgp::{BGPMessage, BGPMessageHeader, SessionContext};
+
+/// This function reads a single message from the stream. If the stream is closed before the message was read completely, this function
+/// returns an error of the kind [io::ErrorKind::UnexpectedEof]. Messages, which can't be decoded or are longer than
+/// [MAX_MESSAGE_LENGTH](crate::protocols::bgp::MAX_MESSAGE_LENGTH) bytes, are returned as error of the kind [io::ErrorKind::InvalidData].
+/// For update messages with inconsistent length fields, the error wraps the [UpdateError](crate::protocols::bgp::UpdateError) to be sent
+/// to the peer.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<BGPMessage> {
+    read_message_with(reader, &SessionContext::four_octet_asn()).await
+}
+
+/// This function reads a single message from the stream like [read_message], but with the maximum length of the message and the encoding
+/// of update messages determined by the capabilities negotiated in the session.
+pub async fn read_message_with<R: AsyncRead + Unpin>(reader: &mut R, context: &SessionContext) -> io::Result<BGPMessage> {
+    let mut bytes = vec![0; BGPMessageHeader::LENGTH];
+    reader.read_exact(&mut bytes).await?;
+    let (_, header) = BGPMessageHeader::peek(&bytes)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid message header => {}", error)))?;
+    if header.length as usize > context.max_message_length() {
+        let message = format!("Message length {} exceeds {} bytes", header.length, context.max_message_length());
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    bytes.resize(header.length as usize, 0);
+    reader.read_exact(&mut bytes[BGPMessageHeader::LENGTH..]).await?;
+    BGPMessage::validate_update(&bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    BGPMessage::unpack_with(&bytes, context)
+        .map(|(_, message)| message)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("Unable to decode message => {}", error)))
+}
+
+/// This function writes the message to the stream. The message is packed into a single buffer, so it's written with as few writes as
+/// possible.
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &BGPMessage) -> io::Result<()> {
+    write_message_with(writer, message, &SessionContext::four_octet_asn()).await
+}
+
+/// This function writes the message to the stream like [write_message], but update messages are encoded with the capabilities negotiated
+/// in the session.
+pub async fn write_message_with<W: AsyncWrite + Unpin>(writer: &mut W, message: &BGPMessage, context: &SessionContext) -> io::Result<()> {
+    writer.write_all(&message.pack_with(context)).await
+}
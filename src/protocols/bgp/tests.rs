@@ -1,17 +1,40 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use crate::prefix::Prefix;
-use crate::protocols::bgp::{BGPMessage, PathAttribute};
-use crate::protocols::bgp::params::OptionalParameter;
-use crate::protocols::bgp::rfc3392::Capability;
-use crate::protocols::bgp::path_attr::Origin;
-use crate::protocols::bgp::rfc4760::{AddressFamily, SubsequentAddressFamily};
+use crate::protocols::bgp::{
+    build_updates, negotiated_hold_time, BGPElement, BGPMessage, BGPMessageHeader, BgpIdentifier, BgpParseError, CeaseSubcode,
+    HoldTimePolicy, NotificationMessage, OpenError, OpenErrorDetail, OpenErrorSubcode, OpenMessage, PackError, ParameterizedBGPElement,
+    PathAttribute, PathAttributeFlags, SessionContext, UpdateError, UpdateErrorSubcode, UpdateMessage, MAX_MESSAGE_LENGTH
+};
+use crate::protocols::bgp::params::{OptionalParameter, ParamParseError};
+use crate::protocols::bgp::rfc2439::{DampingParameters, FlapDamping};
+use crate::protocols::bgp::rfc3392::{negotiate, Capability};
+use crate::protocols::bgp::rfc4364::RouteDistinguisher;
+#[cfg(feature = "vpn")]
+use crate::protocols::bgp::rfc4364::VpnPrefix;
+use crate::protocols::bgp::path_attr::{all_asns, ASPathSegment, Origin};
+use crate::protocols::bgp::rfc1997::{iter_communities, Assignment, Community, CommunityFlags, RouteTarget};
+use crate::protocols::bgp::rfc4760::{
+    AddressFamily, MultiprotocolExtensionsCapability, MultiprotocolNextHop, MultiprotocolReachablePathAttribute,
+    MultiprotocolUnreachablePathAttribute, SubsequentAddressFamily
+};
+use crate::protocols::bgp::rfc6793::FourOctetASNumberSupportCapability;
+#[cfg(feature = "rfc7432")]
+use crate::protocols::bgp::rfc7432::{EvpnRoute, IpPrefixRoute, MacIpAdvertisementRoute};
+use crate::protocols::bgp::rfc8203::ShutdownCommunication;
+#[cfg(feature = "rfc9012")]
+use crate::protocols::bgp::rfc9012::TunnelTlv;
+use crate::protocols::bgp::rfc9494::{LongLivedGracefulRestartCapability, LongLivedGracefulRestartFamily};
+#[cfg(feature = "tokio")]
+use crate::protocols::bgp::stream::{read_message, write_message};
 
 #[test]
 fn read_open_message() {
     let open_message_binary = include_bytes!("test-files/open_message.bin").as_slice();
 
     // Validate open message
-    let BGPMessage::Open(open_message) = BGPMessage::unpack(&open_message_binary).unwrap().1 else {
+    let BGPMessage::Open(open_message) = BGPMessage::unpack(open_message_binary).unwrap().1 else {
         panic!("Test message isn't an open message");
     };
 
@@ -42,8 +65,8 @@ fn read_open_message() {
 
 #[test]
 fn read_update_message_1() {
-    let mut update_message_binary = include_bytes!("test-files/update_message_0.bin").as_slice();
-    let BGPMessage::Update(update_message) = BGPMessage::unpack(&mut update_message_binary).unwrap().1 else {
+    let update_message_binary = include_bytes!("test-files/update_message_0.bin").as_slice();
+    let BGPMessage::Update(update_message) = BGPMessage::unpack(update_message_binary).unwrap().1 else {
         panic!("Test message isn't an update message");
     };
 
@@ -57,15 +80,1499 @@ fn read_update_message_1() {
 
 #[test]
 fn read_update_message_2() {
-    let mut update_message_binary = include_bytes!("test-files/update_message_2.bin").as_slice();
-    let messages = BGPMessage::unpack_many(&mut update_message_binary).unwrap().1;
+    let update_message_binary = include_bytes!("test-files/update_message_2.bin").as_slice();
+    let messages = BGPMessage::unpack_many(update_message_binary).unwrap().1;
     println!("{:#?}", messages);
 }
 
 
 #[test]
 fn read_update_message_3() {
-    let mut update_message_binary = include_bytes!("test-files/update_message_3.bin").as_slice();
-    let messages = BGPMessage::unpack_many(&mut update_message_binary).unwrap().1;
+    let update_message_binary = include_bytes!("test-files/update_message_3.bin").as_slice();
+    let messages = BGPMessage::unpack_many(update_message_binary).unwrap().1;
     println!("{:#?}", messages);
 }
+
+#[test]
+fn pack_open_message() {
+    let open_message = OpenMessage {
+        version: 4,
+        autonomous_system: 65001,
+        hold_time: 90,
+        bgp_identifier: 0xC0000201,
+        optional_parameters: vec![OptionalParameter::Capabilities(vec![
+            Capability::MultiprotocolExtensions(MultiprotocolExtensionsCapability {
+                address_family: AddressFamily::IPv4,
+                subsequent_address_family: SubsequentAddressFamily::Unicast
+            }),
+            Capability::MultiprotocolExtensions(MultiprotocolExtensionsCapability {
+                address_family: AddressFamily::IPv6,
+                subsequent_address_family: SubsequentAddressFamily::Unicast
+            }),
+            Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65001 })
+        ])]
+    };
+
+    let message = BGPMessage::Open(open_message);
+    let bytes = message.pack();
+    assert_eq!(&[0xFF; 16], &bytes[..16]);
+    assert_eq!(bytes.len() as u16, u16::from_be_bytes([bytes[16], bytes[17]]));
+    assert_eq!(1, bytes[18]);
+    assert_eq!((&[][..], message), BGPMessage::unpack(&bytes).unwrap());
+}
+
+#[test]
+fn pack_update_messages() {
+    for update_message_binary in [
+        include_bytes!("test-files/update_message_0.bin").as_slice(),
+        include_bytes!("test-files/update_message_2.bin").as_slice(),
+        include_bytes!("test-files/update_message_3.bin").as_slice()
+    ] {
+        let messages = BGPMessage::unpack_many(update_message_binary).unwrap().1;
+        let bytes: Vec<u8> = messages.iter().flat_map(BGPMessage::pack).collect();
+        assert_eq!(messages, BGPMessage::unpack_many(&bytes).unwrap().1);
+    }
+}
+
+#[test]
+fn round_trip_captures() {
+    let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/src/protocols/bgp/test-files");
+    for entry in std::fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let capture = std::fs::read(&path).unwrap();
+
+        let (_, messages) = BGPMessage::unpack_many(&capture)
+            .unwrap_or_else(|error| panic!("Unable to parse capture {} => {}", path.display(), error));
+
+        for message in messages.iter().filter(|message| !matches!(message, BGPMessage::Unknown { .. })) {
+            let bytes = message.pack();
+            let (remaining, reparsed) = BGPMessage::unpack(&bytes)
+                .unwrap_or_else(|error| panic!("Unable to re-parse message of capture {} => {}", path.display(), error));
+            assert!(remaining.is_empty(), "Re-packed message of capture {} has trailing bytes", path.display());
+            assert_eq!(message, &reparsed, "Message of capture {} doesn't round-trip", path.display());
+        }
+    }
+}
+
+#[test]
+fn shutdown_communication() {
+    let communication = ShutdownCommunication::new("Maintenance of core router, back in 30 minutes").unwrap();
+    let message = BGPMessage::Notification(NotificationMessage::administrative_shutdown(&communication));
+    let BGPMessage::Notification(notification) = BGPMessage::unpack(&message.pack()).unwrap().1 else {
+        panic!("Test message isn't a notification message");
+    };
+
+    assert_eq!((6, 2), (notification.error_code, notification.error_subcode));
+    assert_eq!(Some(communication.message().to_string()), notification.shutdown_communication());
+    assert!(ShutdownCommunication::new("a".repeat(256)).is_err());
+}
+
+#[test]
+fn debug_raw_data_as_hex() {
+    let notification = NotificationMessage { error_code: 6, error_subcode: 2, data: vec![0x0A, 0x0B, 0x0C, 0x0D] };
+    assert!(format!("{:#?}", notification).contains("data: 0a0b 0c0d"));
+    assert!(format!("{:?}", notification).contains("data: [10, 11, 12, 13]"));
+
+    let capability = Capability::Unknown { kind: 70, data: vec![0x0A, 0x0B, 0x0C, 0x0D] };
+    assert!(format!("{:#?}", capability).contains("data: 0a0b 0c0d"));
+    let attribute = PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL, kind: 99, data: vec![0x0A, 0x0B, 0x0C, 0x0D, 0x0E] };
+    assert!(format!("{:#?}", attribute).contains("data: 0a0b 0c0d 0e"));
+}
+
+#[test]
+fn open_error_detail_hold_time() {
+    let notification = NotificationMessage { error_code: 2, error_subcode: 6, data: vec![0x00, 0x02] };
+    assert_eq!(Some(OpenErrorDetail::UnacceptableHoldTime(2)), notification.open_error_detail());
+    assert_eq!(None, NotificationMessage { error_code: 2, error_subcode: 6, data: vec![0x02] }.open_error_detail());
+    assert_eq!(None, NotificationMessage { error_code: 6, error_subcode: 6, data: vec![0x00, 0x02] }.open_error_detail());
+}
+
+#[test]
+fn open_error_detail_unsupported_capability() {
+    let notification = NotificationMessage { error_code: 2, error_subcode: 7, data: vec![0x02, 0x00, 0x41, 0x04, 0x00, 0x00, 0xFD, 0xE9] };
+    let capabilities = vec![Capability::RouteRefresh, Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65001 })];
+    assert_eq!(Some(OpenErrorDetail::UnsupportedCapability(capabilities)), notification.open_error_detail());
+    assert_eq!(None, NotificationMessage { error_code: 2, error_subcode: 7, data: vec![0x02, 0x01] }.open_error_detail());
+}
+
+#[test]
+fn reject_too_many_path_attributes() {
+    let path_attributes: Vec<u8> = [0xC0, 0xFF, 0x00].repeat(200);
+    let mut bytes = vec![0xFF; 16];
+    bytes.extend_from_slice(&((path_attributes.len() + 23) as u16).to_be_bytes());
+    bytes.extend_from_slice(&[2, 0, 0]);
+    bytes.extend_from_slice(&(path_attributes.len() as u16).to_be_bytes());
+    bytes.extend(path_attributes);
+    assert!(BGPMessage::unpack(&bytes).is_err());
+}
+
+#[test]
+fn reject_truncated_as_path_set() {
+    // The set declares 200 AS numbers, but only 3 bytes of data follow
+    let bytes = [0x40, 0x02, 0x05, 0x01, 200, 0x00, 0x00, 0xFD];
+    assert!(PathAttribute::parse(&bytes).is_err());
+    assert!(ASPathSegment::unpack_with(&bytes[3..], true).is_err());
+
+    let segments = [0x02, 0x01, 0x00, 0x00, 0xFD, 0xE9].repeat(65);
+    let mut bytes = vec![0x50, 0x02];
+    bytes.extend_from_slice(&(segments.len() as u16).to_be_bytes());
+    bytes.extend(segments);
+    assert!(PathAttribute::parse(&bytes).is_err());
+}
+
+#[test]
+fn reject_fixed_size_attributes_with_invalid_length() {
+    // NEXT_HOP, MULTI_EXIT_DISC, LOCAL_PREF and ORIGINATOR_ID with 5 bytes instead of 4 bytes
+    for (flags, kind) in [(0x40, 0x03), (0x80, 0x04), (0x40, 0x05), (0x80, 0x09)] {
+        assert!(PathAttribute::parse(&[flags, kind, 0x05, 192, 0, 2, 1, 0]).is_err());
+        assert!(PathAttribute::parse(&[flags, kind, 0x04, 192, 0, 2, 1]).is_ok());
+    }
+    assert!(PathAttribute::parse(&[0x40, 0x01, 0x02, 0x00, 0x00]).is_err());
+
+    // CLUSTER_LIST with a trailing partial cluster ID
+    assert!(PathAttribute::parse(&[0x80, 0x0A, 0x06, 192, 0, 2, 1, 192, 0]).is_err());
+    let (attribute, _) = PathAttribute::parse(&[0x80, 0x0A, 0x08, 192, 0, 2, 1, 192, 0, 2, 2]).unwrap();
+    assert_eq!(PathAttribute::ClusterList(vec![Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)]), attribute);
+}
+
+fn update_message(path_attributes: Vec<PathAttribute>) -> UpdateMessage {
+    UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes,
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/24").unwrap()]
+    }
+}
+
+#[test]
+fn path_preference_local_pref() {
+    let preferred = update_message(vec![PathAttribute::LocalPref(200), PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![1, 2, 3])])]);
+    let other = update_message(vec![PathAttribute::LocalPref(100), PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![1])])]);
+    assert_eq!(Some(200), preferred.local_pref());
+    assert!(preferred.path_preference() < other.path_preference());
+}
+
+#[test]
+fn path_preference_as_path_length() {
+    let preferred = update_message(vec![
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![1]), ASPathSegment::Set(vec![2, 3, 4])]),
+        PathAttribute::Origin(Origin::Incomplete)
+    ]);
+    let other = update_message(vec![PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![1, 2, 3])]), PathAttribute::Origin(Origin::IGP)]);
+    assert_eq!(2, preferred.as_path_len());
+    assert!(preferred.path_preference() < other.path_preference());
+}
+
+#[test]
+fn path_preference_origin() {
+    let preferred = update_message(vec![PathAttribute::Origin(Origin::IGP), PathAttribute::MultiExitDisc(500)]);
+    let other = update_message(vec![PathAttribute::Origin(Origin::EGP), PathAttribute::MultiExitDisc(10)]);
+    assert!(preferred.path_preference() < other.path_preference());
+}
+
+#[test]
+fn path_preference_med() {
+    let preferred = update_message(vec![PathAttribute::Origin(Origin::IGP), PathAttribute::MultiExitDisc(10)]);
+    let other = update_message(vec![PathAttribute::Origin(Origin::IGP), PathAttribute::MultiExitDisc(20)]);
+    assert_eq!(Some(10), preferred.med());
+    assert!(preferred.path_preference() < other.path_preference());
+}
+
+#[test]
+fn subsequent_address_families() {
+    for (value, name) in [(4, "MplsLabels"), (70, "Evpn"), (128, "MplsVpn"), (129, "MulticastVpn"), (133, "FlowSpec"), (200, "Unknown (200)")] {
+        let subsequent_address_family = SubsequentAddressFamily::from(value);
+        assert_eq!(name, subsequent_address_family.to_string());
+        assert_eq!(value, u8::from(subsequent_address_family));
+    }
+
+    let unreachable = MultiprotocolUnreachablePathAttribute::unpack(&[0, 2, 200]).unwrap().1;
+    assert_eq!(SubsequentAddressFamily::Unknown(200), unreachable.subsequent_address_family);
+}
+
+#[test]
+fn address_family_width() {
+    assert_eq!(Some(4), AddressFamily::IPv4.address_width());
+    assert_eq!(Some(16), AddressFamily::IPv6.address_width());
+    assert_eq!(None, AddressFamily::Unknown(25).address_width());
+    assert_eq!(None, AddressFamily::L2VPN.address_width());
+}
+
+#[test]
+fn route_reflection_attributes() {
+    let originator_id = PathAttribute::OriginatorId(Ipv4Addr::new(192, 0, 2, 1));
+    let bytes = originator_id.pack();
+    assert_eq!(vec![0x80, 0x09, 4, 192, 0, 2, 1], bytes);
+    assert_eq!(originator_id, PathAttribute::unpack(&bytes).unwrap().1);
+
+    let cluster_list = PathAttribute::ClusterList(vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)]);
+    let bytes = cluster_list.pack();
+    assert_eq!(vec![0x80, 0x0A, 8, 10, 0, 0, 1, 10, 0, 0, 2], bytes);
+    assert_eq!(cluster_list, PathAttribute::unpack(&bytes).unwrap().1);
+
+    let update_message = update_message(vec![originator_id, cluster_list]);
+    assert!(update_message.has_loop(Ipv4Addr::new(10, 0, 0, 2)));
+    assert!(!update_message.has_loop(Ipv4Addr::new(10, 0, 0, 3)));
+}
+
+#[test]
+fn multiprotocol_next_hop_round_trip() {
+    let next_hops = [
+        (AddressFamily::IPv4, "10.0.0.0/8", "192.0.2.1", None, 4),
+        (AddressFamily::IPv4, "10.0.0.0/8", "192.0.2.1", Some("192.0.2.2"), 8),
+        (AddressFamily::IPv6, "2001:db8::/32", "2001:db8::1", None, 16),
+        (AddressFamily::IPv6, "2001:db8::/32", "2001:db8::1", Some("fe80::1"), 32)
+    ];
+
+    for (address_family, prefix, address, link_local_address, length) in next_hops {
+        let reachable = MultiprotocolReachablePathAttribute {
+            address_family,
+            subsequent_address_family: SubsequentAddressFamily::Unicast,
+            next_hop_address: Some(MultiprotocolNextHop {
+                address: IpAddr::from_str(address).unwrap(),
+                link_local_address: link_local_address.map(|address| IpAddr::from_str(address).unwrap())
+            }),
+            network_layer_reachability_information: vec![Prefix::from_str(prefix).unwrap()],
+            vpn_network_layer_reachability_information: vec![],
+            evpn_network_layer_reachability_information: vec![]
+        };
+
+        let bytes = reachable.pack();
+        assert_eq!(length, bytes[3]);
+        assert_eq!(reachable, MultiprotocolReachablePathAttribute::unpack(&bytes).unwrap().1);
+    }
+}
+
+#[test]
+fn multiprotocol_next_hop_length_by_safi() {
+    let reachable = |subsequent_address_family: u8, next_hop: &[u8]| {
+        let mut bytes = vec![0, 2, subsequent_address_family, next_hop.len() as u8];
+        bytes.extend_from_slice(next_hop);
+        bytes.extend_from_slice(&[0, 32, 0x20, 0x01, 0x0D, 0xB8]);
+        MultiprotocolReachablePathAttribute::unpack(&bytes).map(|(_, reachable)| reachable).ok()
+    };
+    let global = Ipv6Addr::from_str("2001:db8::1").unwrap().octets();
+    let link_local = Ipv6Addr::from_str("fe80::1").unwrap().octets();
+
+    let unicast = reachable(1, &[global, link_local].concat()).unwrap();
+    assert_eq!(SubsequentAddressFamily::Unicast, unicast.subsequent_address_family);
+    assert_eq!(Some(IpAddr::from(link_local)), unicast.next_hop_address.unwrap().link_local_address);
+
+    let multicast = reachable(2, &global).unwrap();
+    assert_eq!(SubsequentAddressFamily::Multicast, multicast.subsequent_address_family);
+    assert_eq!(Some(MultiprotocolNextHop { address: IpAddr::from(global), link_local_address: None }), multicast.next_hop_address);
+
+    // A next hop of 20 bytes is neither one nor two IPv6 addresses
+    assert!(reachable(2, &[global.as_slice(), &[0; 4]].concat()).is_none());
+
+    // FlowSpec routes can be advertised without a next hop, which stays absent instead of becoming the unspecified address
+    let flow_spec = reachable(133, &[]).unwrap();
+    assert_eq!(None, flow_spec.next_hop_address);
+    assert_eq!(vec![0, 2, 133, 0, 0, 32, 0x20, 0x01, 0x0D, 0xB8], flow_spec.pack());
+    assert_eq!(10, flow_spec.wire_len());
+    assert!(reachable(1, &[]).is_none());
+}
+
+#[test]
+#[cfg(feature = "vpn")]
+fn vpn_ipv6_next_hop() {
+    let mut bytes = vec![0, 2, 128, 24, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend(std::net::Ipv6Addr::from_str("2001:db8::1").unwrap().octets());
+    bytes.push(0);
+
+    let reachable = MultiprotocolReachablePathAttribute::unpack(&bytes).unwrap().1;
+    assert_eq!(SubsequentAddressFamily::MplsVpn, reachable.subsequent_address_family);
+    let next_hop = MultiprotocolNextHop { address: IpAddr::from_str("2001:db8::1").unwrap(), link_local_address: None };
+    assert_eq!(Some(next_hop), reachable.next_hop_address);
+    assert_eq!(bytes, reachable.pack());
+}
+
+#[test]
+#[cfg(feature = "vpn")]
+fn vpnv4_network_layer_reachability_information() {
+    let vpn_prefix = VpnPrefix {
+        label_stack: vec![16001],
+        rd: RouteDistinguisher::TwoOctetASN { administrator: 65001, assigned_number: 100 },
+        prefix: Prefix::from_str("10.1.0.0/16").unwrap()
+    };
+    let reachable = MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::IPv4,
+        subsequent_address_family: SubsequentAddressFamily::MplsVpn,
+        next_hop_address: Some(MultiprotocolNextHop { address: IpAddr::from_str("192.0.2.1").unwrap(), link_local_address: None }),
+        network_layer_reachability_information: vec![],
+        vpn_network_layer_reachability_information: vec![vpn_prefix.clone()],
+        evpn_network_layer_reachability_information: vec![]
+    };
+
+    let bytes = reachable.pack();
+    assert_eq!([104, 0x03, 0xE8, 0x11, 0, 0, 0xFD, 0xE9, 0, 0, 0, 100, 10, 1].as_slice(), &bytes[17..]);
+    assert_eq!(bytes.len(), reachable.wire_len());
+    assert_eq!(reachable, MultiprotocolReachablePathAttribute::unpack(&bytes).unwrap().1);
+    assert_eq!("65001:100 10.1.0.0/16 (Labels: 16001)", vpn_prefix.to_string());
+}
+
+#[test]
+#[cfg(feature = "rfc7432")]
+fn evpn_network_layer_reachability_information() {
+    let rd = RouteDistinguisher::IPv4Address { administrator: Ipv4Addr::new(192, 0, 2, 1), assigned_number: 100 };
+    let routes = vec![
+        EvpnRoute::MacIpAdvertisement(MacIpAdvertisementRoute {
+            rd,
+            ethernet_segment_identifier: [0; 10],
+            ethernet_tag: 0,
+            mac_address: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            ip_address: Some(IpAddr::from_str("10.0.0.1").unwrap()),
+            mpls_label1: 10100,
+            mpls_label2: Some(50000)
+        }),
+        EvpnRoute::IpPrefix(IpPrefixRoute {
+            rd,
+            ethernet_segment_identifier: [0; 10],
+            ethernet_tag: 0,
+            prefix: Prefix::from_str("10.1.0.0/16").unwrap(),
+            gateway: IpAddr::from_str("0.0.0.0").unwrap(),
+            mpls_label: 50000
+        }),
+        EvpnRoute::InclusiveMulticast(vec![0, 1, 192, 0, 2, 1, 0, 100, 0, 0, 0, 0, 32, 192, 0, 2, 1])
+    ];
+    let reachable = MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::L2VPN,
+        subsequent_address_family: SubsequentAddressFamily::Evpn,
+        next_hop_address: Some(MultiprotocolNextHop { address: IpAddr::from_str("192.0.2.1").unwrap(), link_local_address: None }),
+        network_layer_reachability_information: vec![],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: routes
+    };
+
+    let bytes = reachable.pack();
+    assert_eq!([0, 25, 70, 4].as_slice(), &bytes[..4]);
+    assert_eq!([2, 40].as_slice(), &bytes[9..11]);
+    assert_eq!([5, 34].as_slice(), &bytes[51..53]);
+    assert_eq!(bytes.len(), reachable.wire_len());
+    assert_eq!(reachable, MultiprotocolReachablePathAttribute::unpack(&bytes).unwrap().1);
+}
+
+#[test]
+#[cfg(feature = "rfc7432")]
+fn reject_ip_prefix_route_with_mask_exceeding_width() {
+    // Route type 5 with an IPv4 prefix, but a mask of 33 bits
+    let mut bytes = vec![5, 34, 0, 1, 192, 0, 2, 1, 0, 100];
+    bytes.extend_from_slice(&[0; 14]);
+    bytes.extend_from_slice(&[33, 10, 1, 0, 0, 0, 0, 0, 0, 0, 0xC3, 0x50]);
+    let error = EvpnRoute::unpack(&bytes).unwrap_err();
+    assert!(matches!(error, nom::Err::Error(error) if error.code == nom::error::ErrorKind::Verify));
+
+    bytes[24] = 16;
+    assert_eq!(Prefix::from_str("10.1.0.0/16").unwrap(), match EvpnRoute::unpack(&bytes).unwrap().1 {
+        EvpnRoute::IpPrefix(route) => route.prefix,
+        route => panic!("Unexpected route {route:?}")
+    });
+}
+
+#[test]
+fn route_distinguishers() {
+    for (route_distinguisher, name) in [
+        (RouteDistinguisher::TwoOctetASN { administrator: 65001, assigned_number: 100 }, "65001:100"),
+        (RouteDistinguisher::IPv4Address { administrator: Ipv4Addr::new(192, 0, 2, 1), assigned_number: 100 }, "192.0.2.1:100"),
+        (RouteDistinguisher::FourOctetASN { administrator: 4200000000, assigned_number: 100 }, "4200000000:100")
+    ] {
+        let bytes = route_distinguisher.pack();
+        assert_eq!(8, bytes.len());
+        assert_eq!(route_distinguisher, RouteDistinguisher::unpack(&bytes).unwrap().1);
+        assert_eq!(name, route_distinguisher.to_string());
+    }
+}
+
+#[test]
+fn create_open_message() {
+    let identifier = Ipv4Addr::new(192, 0, 2, 1);
+    let open_message = OpenMessage::new(65001, 90, identifier, vec![]).unwrap();
+    assert_eq!((4, 90, 0xC0000201), (open_message.version, open_message.hold_time, open_message.bgp_identifier));
+    assert!(OpenMessage::new(65001, 0, identifier, vec![]).is_ok());
+    assert!(OpenMessage::new(65001, 3, identifier, vec![]).is_ok());
+    assert_eq!(Err(OpenError::UnacceptableHoldTime(1)), OpenMessage::new(65001, 1, identifier, vec![]));
+    assert_eq!(Err(OpenError::UnacceptableHoldTime(2)), OpenMessage::new(65001, 2, identifier, vec![]));
+    assert_eq!(Err(OpenError::BadBgpIdentifier(Ipv4Addr::UNSPECIFIED)), OpenMessage::new(65001, 90, Ipv4Addr::UNSPECIFIED, vec![]));
+    let multicast = Ipv4Addr::new(224, 0, 0, 5);
+    assert_eq!(Err(OpenError::BadBgpIdentifier(multicast)), OpenMessage::new(65001, 90, multicast, vec![]));
+}
+
+#[test]
+fn bgp_identifier_ip() {
+    let mut open_message = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![]).unwrap();
+    open_message.bgp_identifier = 0x0A000001;
+    assert_eq!(Ipv4Addr::new(10, 0, 0, 1), open_message.bgp_identifier_ip());
+    assert_eq!("10.0.0.1", BgpIdentifier(open_message.bgp_identifier_ip()).to_string());
+}
+
+#[test]
+fn bgp_identifier_local_addresses() {
+    let fib_trie = "Main:\n  +-- 0.0.0.0/0 3 0 5\n     |-- 127.0.0.1\n        /32 host LOCAL\n     |-- 192.0.2.0\n        /24 link UNICAST\n     \
+                    |-- 192.0.2.2\n        /32 host LOCAL\n     |-- 192.0.2.255\n        /32 link BROADCAST\n";
+    assert_eq!(vec![Ipv4Addr::new(127, 0, 0, 1), Ipv4Addr::new(192, 0, 2, 2)], BgpIdentifier::local_addresses(fib_trie));
+}
+
+#[test]
+fn raw_capability() {
+    let capability = Capability::raw(128, (0..10).collect()).unwrap();
+    let bytes = capability.pack();
+    assert_eq!(&[128, 10], &bytes[..2]);
+    assert_eq!(capability, Capability::unpack(&bytes).unwrap().1);
+    assert!(Capability::raw(128, vec![0; 256]).is_err());
+}
+
+#[test]
+fn message_type_names() {
+    let messages = [
+        (BGPMessage::Open(OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![]).unwrap()), 1, "OPEN"),
+        (BGPMessage::Update(update_message(vec![])), 2, "UPDATE"),
+        (BGPMessage::Notification(NotificationMessage { error_code: 6, error_subcode: 2, data: vec![] }), 3, "NOTIFICATION"),
+        (BGPMessage::KeepAlive, 4, "KEEPALIVE"),
+        (BGPMessage::Unknown { kind: 5, data: vec![0, 1, 0, 1] }, 5, "ROUTE-REFRESH"),
+        (BGPMessage::Unknown { kind: 42, data: vec![] }, 42, "UNKNOWN")
+    ];
+
+    for (message, kind, name) in messages {
+        assert_eq!(kind, message.kind());
+        assert_eq!(name, message.type_name());
+    }
+}
+
+#[test]
+fn confederation_path_segments() {
+    let bytes = [0x40, 0x02, 16, 3, 2, 0, 0, 0xFD, 0xE9, 0, 0, 0xFD, 0xEA, 2, 1, 0, 0, 0xFD, 0xEB];
+    let attribute = PathAttribute::unpack(&bytes).unwrap().1;
+    assert_eq!(PathAttribute::ASPath(vec![ASPathSegment::ConfedSequence(vec![65001, 65002]), ASPathSegment::Sequence(vec![65003])]), attribute);
+    assert_eq!(bytes.to_vec(), attribute.pack());
+    assert_eq!(1, update_message(vec![attribute]).as_path_len());
+}
+
+#[test]
+fn normalize_mapped_next_hop() {
+    let next_hop = MultiprotocolNextHop { address: IpAddr::from_str("::ffff:192.0.2.1").unwrap(), link_local_address: None };
+    assert_eq!(IpAddr::from_str("192.0.2.1").unwrap(), next_hop.normalized().address);
+
+    let next_hop = MultiprotocolNextHop { address: IpAddr::from_str("2001:db8::1").unwrap(), link_local_address: None };
+    assert_eq!(next_hop, next_hop.normalized());
+}
+
+#[test]
+fn build_update_messages() {
+    let attributes = vec![PathAttribute::Origin(Origin::IGP), PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1))];
+    let reordered_attributes = vec![PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)), PathAttribute::Origin(Origin::IGP)];
+    let other_attributes = vec![PathAttribute::Origin(Origin::EGP), PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1))];
+    let routes = vec![
+        (Prefix::from_str("10.0.0.0/24").unwrap(), attributes.clone()),
+        (Prefix::from_str("10.0.1.0/24").unwrap(), other_attributes.clone()),
+        (Prefix::from_str("10.0.2.0/24").unwrap(), reordered_attributes)
+    ];
+
+    let updates = build_updates(&routes).unwrap();
+    assert_eq!(2, updates.len());
+    assert_eq!(vec![Prefix::from_str("10.0.0.0/24").unwrap(), Prefix::from_str("10.0.2.0/24").unwrap()], updates[0].network_layer_reachability_information);
+    assert_eq!(attributes, updates[0].path_attributes);
+    assert_eq!(vec![Prefix::from_str("10.0.1.0/24").unwrap()], updates[1].network_layer_reachability_information);
+    assert_eq!(other_attributes, updates[1].path_attributes);
+
+    // 2000 prefixes with 4 bytes each don't fit into a single message
+    let routes: Vec<(Prefix, Vec<PathAttribute>)> = (0..2000u32)
+        .map(|index| (Prefix::from_str(&format!("{}/24", Ipv4Addr::from_bits(0x0A000000 | (index << 8)))).unwrap(), attributes.clone()))
+        .collect();
+    let updates = build_updates(&routes).unwrap();
+    assert_eq!(2, updates.len());
+    assert_eq!(2000, updates.iter().map(|update| update.network_layer_reachability_information.len()).sum::<usize>());
+    assert!(updates.iter().all(|update| BGPMessage::Update(update.clone()).pack().len() <= MAX_MESSAGE_LENGTH));
+
+    // IPv6 routes are advertised in the MP_REACH_NLRI path attribute carrying the next hop, but not grouped with IPv4 routes
+    let reachable = MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        next_hop_address: Some(MultiprotocolNextHop { address: IpAddr::from_str("2001:db8::1").unwrap(), link_local_address: None }),
+        network_layer_reachability_information: vec![],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    };
+    let ipv6_attributes = vec![PathAttribute::Origin(Origin::IGP), PathAttribute::MpReachableNLRI(reachable.clone())];
+    let routes = vec![
+        (Prefix::from_str("10.0.0.0/24").unwrap(), ipv6_attributes.clone()),
+        (Prefix::from_str("2001:db8:1::/48").unwrap(), ipv6_attributes.clone()),
+        (Prefix::from_str("2001:db8:2::/48").unwrap(), ipv6_attributes.clone())
+    ];
+    let updates = build_updates(&routes).unwrap();
+    assert_eq!(2, updates.len());
+    assert_eq!(vec![Prefix::from_str("10.0.0.0/24").unwrap()], updates[0].network_layer_reachability_information);
+    assert!(updates[1].network_layer_reachability_information.is_empty());
+    let PathAttribute::MpReachableNLRI(announced) = &updates[1].path_attributes[1] else { panic!("Expected MP_REACH_NLRI attribute") };
+    assert_eq!(vec![Prefix::from_str("2001:db8:1::/48").unwrap(), Prefix::from_str("2001:db8:2::/48").unwrap()],
+        announced.network_layer_reachability_information);
+
+    // 1000 IPv6 prefixes with 7 bytes each are split like IPv4 prefixes
+    let routes: Vec<(Prefix, Vec<PathAttribute>)> = (0..1000u128)
+        .map(|index| (Prefix::new(IpAddr::V6(Ipv6Addr::from_bits(0x2001_0db8 << 96 | index << 80)), 48), ipv6_attributes.clone()))
+        .collect();
+    let updates = build_updates(&routes).unwrap();
+    assert_eq!(2, updates.len());
+    assert_eq!(1000, updates.iter().map(|update| match &update.path_attributes[1] {
+        PathAttribute::MpReachableNLRI(announced) => announced.route_count(),
+        _ => 0
+    }).sum::<usize>());
+    assert!(updates.iter().all(|update| BGPMessage::Update(update.clone()).pack().len() <= MAX_MESSAGE_LENGTH));
+
+    let routes = vec![(Prefix::from_str("2001:db8:1::/48").unwrap(), attributes.clone())];
+    assert_eq!(Err(PackError::MissingAttribute { element: "IPv6 route", kind: 14 }), build_updates(&routes));
+
+    // Path attributes, which leave no space for a route, are rejected instead of building oversized messages
+    let community = Community::RFC1997 { global_administrator: 65001, local_administrator: 100 };
+    let oversized_attributes = vec![PathAttribute::Origin(Origin::IGP), PathAttribute::Communities(vec![community; 1100])];
+    let routes = vec![(Prefix::from_str("10.0.0.0/24").unwrap(), oversized_attributes)];
+    let error = PackError::TooLong { element: "Message", length: 4435, maximum: MAX_MESSAGE_LENGTH };
+    assert_eq!(Err(error), build_updates(&routes));
+}
+
+#[test]
+#[cfg(feature = "rfc9012")]
+fn tunnel_encapsulation_attribute() {
+    // VXLAN tunnel with the encapsulation (VNI 100) and tunnel egress endpoint (192.0.2.1) sub-TLVs and a sub-TLV with a two-byte length
+    let bytes = [
+        0xC0, 0x17, 0x1E, 0x00, 0x08, 0x00, 0x1A,
+        0x01, 0x08, 0x08, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00,
+        0x06, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xC0, 0x00, 0x02, 0x01,
+        0x80, 0x00, 0x01, 0xFF
+    ];
+
+    let (_, attribute) = PathAttribute::unpack(&bytes).unwrap();
+    let PathAttribute::TunnelEncapsulation(tunnels) = &attribute else { panic!("Expected tunnel encapsulation attribute") };
+    assert_eq!(vec![TunnelTlv {
+        tunnel_type: 8,
+        sub_tlvs: vec![
+            (0x01, vec![0x08, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00]),
+            (0x06, vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xC0, 0x00, 0x02, 0x01]),
+            (0x80, vec![0xFF])
+        ]
+    }], *tunnels);
+    assert_eq!(bytes.as_slice(), attribute.pack());
+    assert_eq!(bytes.len(), attribute.wire_len());
+}
+
+#[test]
+fn end_of_rib() {
+    let bytes = BGPMessage::Update(UpdateMessage::end_of_rib(AddressFamily::IPv4, SubsequentAddressFamily::Unicast)).pack();
+    let mut expected = vec![0xFF; 16];
+    expected.extend_from_slice(&[0, 23, 2, 0, 0, 0, 0]);
+    assert_eq!(expected, bytes);
+
+    let end_of_rib = UpdateMessage::end_of_rib(AddressFamily::IPv6, SubsequentAddressFamily::Unicast);
+    assert_eq!(vec![0x80, 0x0F, 3, 0, 2, 1], end_of_rib.pack()[4..]);
+}
+
+#[test]
+fn empty_update_message() {
+    let empty = UpdateMessage { withdrawn_routes: vec![], path_attributes: vec![], network_layer_reachability_information: vec![] };
+    assert_eq!(vec![0, 0, 0, 0], empty.pack());
+    assert_eq!(Ok((empty.clone(), &[][..])), UpdateMessage::parse(&[0, 0, 0, 0]));
+    assert!(UpdateMessage::parse(&[0, 0, 0]).is_err());
+
+    let mut context = SessionContext::default();
+    context.add_path.insert((AddressFamily::IPv4, SubsequentAddressFamily::Unicast));
+    assert_eq!(Ok((empty.clone(), &[][..])), UpdateMessage::parse_with(&[0, 0, 0, 0], &context));
+    assert_eq!(Ok(()), empty.validate_received());
+    assert_eq!(Ok((BGPMessage::Update(empty.clone()), &[][..])), BGPMessage::parse(&BGPMessage::Update(empty).pack()));
+}
+
+#[test]
+fn detect_end_of_rib() {
+    let BGPMessage::Update(update) = BGPMessage::unpack(&[[0xFF; 16].as_slice(), &[0, 23, 2, 0, 0, 0, 0]].concat()).unwrap().1 else {
+        panic!("Expected UPDATE message");
+    };
+    assert_eq!(Some((AddressFamily::IPv4, SubsequentAddressFamily::Unicast)), update.is_end_of_rib());
+
+    let bytes = UpdateMessage::end_of_rib(AddressFamily::IPv6, SubsequentAddressFamily::Unicast).pack();
+    let (update, _) = UpdateMessage::parse(&bytes).unwrap();
+    assert_eq!(Some((AddressFamily::IPv6, SubsequentAddressFamily::Unicast)), update.is_end_of_rib());
+
+    let mut update = UpdateMessage::end_of_rib(AddressFamily::IPv6, SubsequentAddressFamily::Unicast);
+    update.withdrawn_routes.push(Prefix::from_str("192.0.2.0/24").unwrap());
+    assert_eq!(None, update.is_end_of_rib());
+    let PathAttribute::MpUnreachableNLRI(unreachable) = &mut update.path_attributes[0] else { panic!("Expected MP_UNREACH_NLRI") };
+    unreachable.network_layer_reachability_information.push(Prefix::from_str("2001:db8::/32").unwrap());
+    update.withdrawn_routes.clear();
+    assert_eq!(None, update.is_end_of_rib());
+}
+
+#[test]
+fn pack_default_route_announcement() {
+    let update_message = UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes: vec![PathAttribute::Origin(Origin::IGP)],
+        network_layer_reachability_information: vec![Prefix::ANY_IPV4]
+    };
+
+    let message = BGPMessage::Update(update_message);
+    let bytes = message.pack();
+    assert_eq!(&[0, 0, 0, 4, 0x40, 0x01, 1, 0, 0], &bytes[19..]);
+    assert_eq!(message, BGPMessage::unpack(&bytes).unwrap().1);
+}
+
+#[test]
+fn malformed_capability_length() {
+    let capability = Capability::unpack(&[1, 3, 0, 1, 1]).unwrap().1;
+    assert_eq!(Capability::Malformed { kind: 1, expected: 4, data: vec![0, 1, 1] }, capability);
+    assert_eq!(vec![1, 3, 0, 1, 1], capability.pack());
+
+    let capabilities = OptionalParameter::unpack(&[2, 10, 65, 2, 0, 1, 1, 4, 0, 1, 0, 1], false).unwrap().1;
+    let OptionalParameter::Capabilities(capabilities) = capabilities else {
+        panic!("Optional parameter isn't a capabilities parameter");
+    };
+    assert!(matches!(capabilities[0], Capability::Malformed { kind: 65, expected: 4, .. }));
+    assert!(matches!(capabilities[1], Capability::MultiprotocolExtensions(_)));
+}
+
+#[test]
+fn negotiate_route_refresh() {
+    let identifier = Ipv4Addr::new(192, 0, 2, 1);
+    let capabilities = vec![Capability::RouteRefresh, Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65001 })];
+    let local = OpenMessage::new(65001, 90, identifier, vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let bytes = BGPMessage::Open(local.clone()).pack();
+    assert_eq!(BGPMessage::Open(local.clone()), BGPMessage::unpack(&bytes).unwrap().1);
+
+    let capabilities = vec![Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65002 }), Capability::RouteRefresh];
+    let remote = OpenMessage::new(65002, 90, Ipv4Addr::new(192, 0, 2, 2), vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let negotiated = negotiate(&local, &remote);
+    assert_eq!(2, negotiated.len());
+    assert!(negotiated.contains(&Capability::RouteRefresh));
+
+    let remote = OpenMessage::new(65002, 90, Ipv4Addr::new(192, 0, 2, 2), vec![]).unwrap();
+    assert!(negotiate(&local, &remote).is_empty());
+}
+
+#[test]
+fn pack_into_reused_buffer() {
+    let update_message_binary = include_bytes!("test-files/update_message_2.bin").as_slice();
+    let messages = BGPMessage::unpack_many(update_message_binary).unwrap().1;
+    let expected: Vec<Vec<u8>> = messages.iter().map(BGPMessage::pack).collect();
+
+    let mut buffer = Vec::with_capacity(4096);
+    let capacity = buffer.capacity();
+    for index in 0..1000 {
+        buffer.clear();
+        messages[index % messages.len()].pack_into(&mut buffer);
+        assert_eq!(expected[index % messages.len()], buffer);
+    }
+    assert_eq!(capacity, buffer.capacity());
+}
+
+#[test]
+fn attributes_equivalent_ignores_order() {
+    let community = |local_administrator| Community::RFC1997 { global_administrator: 65001, local_administrator };
+    let update = update_message(vec![
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])]),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+        PathAttribute::Communities(vec![community(1), community(2), community(3)])
+    ]);
+    let reordered = update_message(vec![
+        PathAttribute::Communities(vec![community(3), community(1), community(2)]),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])])
+    ]);
+    assert_ne!(update, reordered);
+    assert!(update.attributes_equivalent(&reordered));
+    assert!(reordered.attributes_equivalent(&update));
+
+    let changed = update_message(vec![
+        PathAttribute::Communities(vec![community(3), community(1), community(4)]),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])])
+    ]);
+    assert!(!update.attributes_equivalent(&changed));
+
+    let duplicated = update_message(vec![
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+        PathAttribute::Communities(vec![community(1), community(2), community(3)])
+    ]);
+    assert!(!update.attributes_equivalent(&duplicated));
+}
+
+#[test]
+fn prepare_for_ebgp() {
+    let mut update = update_message(vec![
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::ConfedSequence(vec![64512]), ASPathSegment::Sequence(vec![65002, 65003])]),
+        PathAttribute::NextHop(Ipv4Addr::new(10, 0, 0, 1)),
+        PathAttribute::MultiExitDisc(50),
+        PathAttribute::LocalPref(200)
+    ]);
+    update.prepare_for_ebgp(65001, IpAddr::from_str("192.0.2.1").unwrap());
+    assert_eq!(None, update.med());
+    assert_eq!(None, update.local_pref());
+    assert_eq!(3, update.as_path_len());
+    assert_eq!(vec![
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002, 65003])]),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1))
+    ], update.path_attributes);
+
+    let mut update = update_message(vec![PathAttribute::ASPath(vec![ASPathSegment::Set(vec![65002, 65003])])]);
+    update.prepare_for_ebgp(65001, IpAddr::from_str("192.0.2.1").unwrap());
+    assert_eq!(vec![
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001]), ASPathSegment::Set(vec![65002, 65003])]),
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1))
+    ], update.path_attributes);
+}
+
+#[test]
+fn prepare_for_ebgp_multiprotocol_next_hop() {
+    let mut update = update_message(vec![PathAttribute::MpReachableNLRI(MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        next_hop_address: Some(MultiprotocolNextHop {
+            address: IpAddr::from_str("2001:db8::2").unwrap(),
+            link_local_address: Some(IpAddr::from_str("fe80::2").unwrap())
+        }),
+        network_layer_reachability_information: vec![Prefix::from_str("2001:db8:1::/48").unwrap()],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    })]);
+    update.prepare_for_ebgp(65001, IpAddr::from_str("2001:db8::1").unwrap());
+    let PathAttribute::MpReachableNLRI(reachable) = &update.path_attributes[0] else { panic!("Expected MP_REACH_NLRI") };
+    let next_hop = MultiprotocolNextHop { address: IpAddr::from_str("2001:db8::1").unwrap(), link_local_address: None };
+    assert_eq!(Some(next_hop), reachable.next_hop_address);
+    assert_eq!(PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001])]), update.path_attributes[1]);
+}
+
+#[test]
+fn prepare_for_ibgp() {
+    let as_path = PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65002])]);
+    let mut update = update_message(vec![as_path.clone(), PathAttribute::LocalPref(100)]);
+    update.prepare_for_ibgp(300);
+    assert_eq!(vec![as_path.clone(), PathAttribute::LocalPref(300)], update.path_attributes);
+
+    let mut update = update_message(vec![as_path.clone()]);
+    update.prepare_for_ibgp(300);
+    assert_eq!(Some(300), update.local_pref());
+    assert_eq!(1, update.as_path_len());
+}
+
+#[test]
+fn unpack_diagnostic_truncated_update() {
+    let mut bytes = BGPMessage::Update(update_message(vec![
+        PathAttribute::Origin(Origin::IGP),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])])
+    ])).pack();
+
+    // The message is cut off inside the AS_PATH path attribute, while the lengths in the header and the update message are kept
+    bytes.truncate(bytes.len() - 8);
+    let length = bytes.len() as u16;
+    bytes[16..18].copy_from_slice(&length.to_be_bytes());
+
+    let diagnostic = BGPMessage::unpack_diagnostic(&bytes).unwrap_err();
+    let attributes_start = 19 + 2 + 2;
+    assert!(diagnostic.offset >= attributes_start && diagnostic.offset <= bytes.len(), "{}", diagnostic);
+    assert!(diagnostic.snippet.contains('['));
+    assert!(BGPMessage::unpack_diagnostic(&BGPMessage::KeepAlive.pack()).is_ok());
+}
+
+#[test]
+fn parse_elements() {
+    let bytes = BGPMessage::KeepAlive.pack();
+    assert_eq!(Ok((BGPMessage::KeepAlive, [].as_slice())), BGPMessage::parse(&bytes));
+    assert_eq!(Err(BgpParseError::Truncated { offset: 16 }), BGPMessage::parse(&bytes[..17]));
+
+    let mut bytes = BGPMessage::KeepAlive.pack();
+    bytes[17] = 18;
+    assert_eq!(Err(BgpParseError::BadTag { offset: 16 }), BGPMessage::parse(&bytes));
+
+    let bytes = BGPMessage::Unknown { kind: 42, data: vec![1, 2] }.pack();
+    assert_eq!(Err(BgpParseError::UnsupportedMessageType { offset: BGPMessageHeader::LENGTH - 1, kind: 42 }), BGPMessage::parse(&bytes));
+
+    let bytes = [2, 0, 0xFF];
+    assert_eq!(Ok((Capability::RouteRefresh, [0xFF].as_slice())), Capability::parse(&bytes));
+}
+
+#[test]
+fn peek_message_header() {
+    let bytes = BGPMessage::KeepAlive.pack();
+    assert_eq!(Some(4), BGPMessage::message_type(&bytes));
+    assert_eq!(None, BGPMessage::message_type(&bytes[..18]));
+
+    let (remaining, header) = BGPMessageHeader::peek(&bytes).unwrap();
+    assert_eq!(BGPMessageHeader { marker: BGPMessageHeader::STANDARD_MARKER, length: 19, kind: 4 }, header);
+    assert!(remaining.is_empty());
+}
+
+fn open_message_body(optional_parameters: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![4, 0xFD, 0xE9, 0, 90, 192, 0, 2, 1];
+    bytes.extend_from_slice(optional_parameters);
+    bytes
+}
+
+#[test]
+#[cfg(feature = "rfc9072")]
+fn unpack_open_extended_parameters() {
+    let capabilities = [2, 0, 65, 4, 0, 0, 0xFD, 0xE9];
+    let mut optional_parameters = vec![255, 255, 0, 11, 2, 0, 8];
+    optional_parameters.extend_from_slice(&capabilities);
+
+    let mut bytes = vec![0xFF; 16];
+    bytes.extend_from_slice(&[0, (19 + 9 + optional_parameters.len()) as u8, 1]);
+    bytes.extend(open_message_body(&optional_parameters));
+    let BGPMessage::Open(open) = BGPMessage::unpack(&bytes).unwrap().1 else { panic!("Expected OPEN message") };
+    assert_eq!(65001, open.autonomous_system);
+    assert_eq!(vec![
+        &Capability::RouteRefresh,
+        &Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65001 })
+    ], open.capabilities().collect::<Vec<_>>());
+
+    let mut legacy = vec![10, 2, 8];
+    legacy.extend_from_slice(&capabilities);
+    let legacy = OpenMessage::unpack_lenient(&open_message_body(&legacy)).unwrap().1.0;
+    assert_eq!(open, legacy);
+}
+
+#[test]
+fn unpack_open_lenient() {
+    // The first capabilities parameter is valid, the second one contains a truncated capability and the unknown parameter exceeds the
+    // remaining bytes.
+    let optional_parameters = [16, 2, 2, 2, 0, 2, 4, 65, 4, 0, 0, 3, 8, 1, 2, 3, 4];
+    let (_, (open, errors)) = OpenMessage::unpack_lenient(&open_message_body(&optional_parameters)).unwrap();
+    assert_eq!(vec![OptionalParameter::Capabilities(vec![Capability::RouteRefresh])], open.optional_parameters);
+    assert_eq!(vec![
+        ParamParseError::Malformed { kind: 2, data: vec![65, 4, 0, 0] },
+        ParamParseError::Truncated { kind: Some(3), length: Some(8), available: 4 }
+    ], errors);
+}
+
+#[test]
+fn unpack_open_authentication_parameter() {
+    let optional_parameters = [6, 1, 4, 0xFF, 0xDE, 0xAD, 0xBE];
+    let (_, open) = OpenMessage::unpack(&open_message_body(&optional_parameters)).unwrap();
+    let parameter = OptionalParameter::Authentication { auth_code: 0xFF, data: vec![0xDE, 0xAD, 0xBE] };
+    assert_eq!(vec![parameter.clone()], open.optional_parameters);
+    assert_eq!(optional_parameters[1..], parameter.pack(false));
+
+    let (_, (_, errors)) = OpenMessage::unpack_lenient(&open_message_body(&[2, 1, 0])).unwrap();
+    assert_eq!(vec![ParamParseError::Malformed { kind: 1, data: vec![] }], errors);
+}
+
+#[test]
+#[cfg(feature = "rfc9072")]
+fn pack_open_extended_parameters() {
+    // 50 capabilities with 4 bytes of data result in a capabilities block of 300 bytes
+    let capabilities: Vec<Capability> = (0..50).map(|index| Capability::raw(200, vec![0, 0, 0, index]).unwrap()).collect();
+    let open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let bytes = BGPMessage::Open(open.clone()).pack();
+    assert_eq!(19 + 9 + 4 + 3 + 300, bytes.len());
+    assert_eq!([255, 255, 0x01, 0x2F, 2, 0x01, 0x2C], bytes[28..35]);
+    assert_eq!(BGPMessage::Open(open), BGPMessage::unpack(&bytes).unwrap().1);
+
+    let capabilities = vec![Capability::RouteRefresh];
+    let open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let bytes = BGPMessage::Open(open.clone()).pack();
+    assert_eq!([4, 2, 2, 2, 0], bytes[28..]);
+    assert_eq!(BGPMessage::Open(open), BGPMessage::unpack(&bytes).unwrap().1);
+}
+
+#[test]
+#[cfg(not(feature = "rfc9072"))]
+fn reject_open_extended_parameters_without_rfc9072() {
+    let capabilities: Vec<Capability> = (0..50).map(|index| Capability::raw(200, vec![0, 0, 0, index]).unwrap()).collect();
+    let open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let error = PackError::TooLong { element: "Optional parameter", length: 300, maximum: 255 };
+    assert_eq!(Err(error), BGPMessage::Open(open).try_pack());
+
+    // Without RFC 9072, a first parameter of type 255 is an unknown parameter instead of the extended length marker
+    let bytes = open_message_body(&[4, 255, 2, 0, 0]);
+    let open = OpenMessage::unpack(&bytes).unwrap().1;
+    assert_eq!(vec![OptionalParameter::Unknown { kind: 255, data: vec![0, 0] }], open.optional_parameters);
+}
+
+#[test]
+fn contains_asn_in_set() {
+    let segments = vec![ASPathSegment::Sequence(vec![65002, 65003]), ASPathSegment::Set(vec![65001, 65005])];
+    assert_eq!(vec![65002, 65003, 65001, 65005], all_asns(&segments).collect::<Vec<_>>());
+
+    let update = update_message(vec![PathAttribute::ASPath(segments)]);
+    assert!(update.contains_asn(65001));
+    assert!(update.contains_asn(65003));
+    assert!(!update.contains_asn(65004));
+    assert!(!update_message(vec![]).contains_asn(65001));
+}
+
+#[test]
+fn extended_community_route_target() {
+    let community = Community::unpack(&[0x00, 0x02, 0xFD, 0xE9, 0, 0, 0, 100], true).unwrap().1;
+    let route_target = community.as_route_target().unwrap();
+    assert_eq!(RouteTarget::from_str("rt:65001:100").unwrap(), route_target);
+    assert_eq!("rt:65001:100", route_target.to_string());
+
+    let community = Community::unpack(&[0x02, 0x02, 0, 1, 0, 0, 0, 1], true).unwrap().1;
+    assert_eq!(Some(RouteTarget::ASN { asn: 65536, value: 1 }), community.as_route_target());
+    let community = Community::unpack(&[0x01, 0x02, 192, 0, 2, 1, 0, 7], true).unwrap().1;
+    assert_eq!(Some(RouteTarget::from_str("rt:192.0.2.1:7").unwrap()), community.as_route_target());
+
+    // Route origin and standard communities aren't route targets
+    assert_eq!(None, Community::unpack(&[0x00, 0x03, 0xFD, 0xE9, 0, 0, 0, 100], true).unwrap().1.as_route_target());
+    assert_eq!(None, Community::unpack(&[0xFD, 0xE9, 0, 100], false).unwrap().1.as_route_target());
+    assert!(RouteTarget::from_str("rt:65536:65536").is_err());
+    assert!(RouteTarget::from_str("65001:100").is_err());
+}
+
+#[test]
+fn iterate_communities_lazily() {
+    let communities = vec![
+        Community::RFC1997 { global_administrator: 65001, local_administrator: 100 },
+        Community::RFC1997 { global_administrator: 65535, local_administrator: 65281 },
+        Community::LLGR_STALE
+    ];
+    let bytes = PathAttribute::Communities(communities.clone()).pack();
+    let (attribute, _) = PathAttribute::parse(&bytes).unwrap();
+    assert_eq!(PathAttribute::Communities(iter_communities(&bytes[3..]).collect()), attribute);
+    assert_eq!(communities, iter_communities(&bytes[3..]).collect::<Vec<_>>());
+
+    let no_export = Community::RFC1997 { global_administrator: 65535, local_administrator: 65281 };
+    assert_eq!(Some(1), iter_communities(&bytes[3..]).position(|community| community == no_export));
+    assert_eq!(0, iter_communities(&[0xFD, 0xE9]).count());
+}
+
+#[test]
+fn sort_attributes_by_type_code() {
+    let mut update = update_message(vec![
+        PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+        PathAttribute::LocalPref(100),
+        PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001])]),
+        PathAttribute::Origin(Origin::IGP)
+    ]);
+    update.sort_attributes();
+    let attribute_kinds: Vec<u8> = update.path_attributes.iter().map(|attribute| attribute.pack()[1]).collect();
+    assert_eq!(vec![1, 2, 3, 5], attribute_kinds);
+
+    // The AS_PATH attribute (type code 2) directly follows the ORIGIN attribute (4 bytes) after the withdrawn routes and the length
+    let bytes = update.pack();
+    assert_eq!([0x40, 2], bytes[8..10]);
+    assert_eq!(PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)).pack(), bytes[17..24]);
+}
+
+#[test]
+fn flap_damping() {
+    let parameters = DampingParameters { penalty: 1000.0, suppress_threshold: 2000.0, reuse_threshold: 750.0, half_life: Duration::from_secs(60) };
+    let mut damping = FlapDamping::new(parameters);
+    let prefix = Prefix::from_str("192.0.2.0/24").unwrap();
+    let start = Instant::now();
+
+    // Rapid flaps push the penalty above the suppress threshold
+    damping.record_flap(prefix, start);
+    damping.record_flap(prefix, start + Duration::from_secs(1));
+    assert!(!damping.is_suppressed(&prefix, start + Duration::from_secs(1)));
+    damping.record_flap(prefix, start + Duration::from_secs(2));
+    assert!(damping.is_suppressed(&prefix, start + Duration::from_secs(2)));
+    assert!(!damping.is_suppressed(&Prefix::from_str("198.51.100.0/24").unwrap(), start));
+
+    // After one half-life, the penalty stays above the reuse threshold and after two half-lives it decays below it
+    assert!(damping.is_suppressed(&prefix, start + Duration::from_secs(62)));
+    assert!(!damping.is_suppressed(&prefix, start + Duration::from_secs(122)));
+
+    // A single flap after reuse doesn't suppress the prefix again
+    damping.record_flap(prefix, start + Duration::from_secs(122));
+    assert!(!damping.is_suppressed(&prefix, start + Duration::from_secs(122)));
+
+    damping.prune(start + Duration::from_secs(600));
+    assert_eq!(0.0, damping.penalty(&prefix, start + Duration::from_secs(600)));
+}
+
+#[test]
+fn unpack_update_with_session_context() {
+    // AS_PATH with a sequence of 2 AS numbers encoded with two bytes and an NLRI prefixed with the path identifier 1
+    let bytes = [
+        0x00, 0x00, 0x00, 0x09, 0x40, 0x02, 0x06, 0x02, 0x02, 0xFD, 0xE9, 0xFD, 0xEA,
+        0x00, 0x00, 0x00, 0x01, 0x18, 0xC0, 0x00, 0x02
+    ];
+    let mut context = SessionContext::default();
+    context.add_path.insert((AddressFamily::IPv4, SubsequentAddressFamily::Unicast));
+    let (update, remaining) = UpdateMessage::parse_with(&bytes, &context).unwrap();
+    assert!(remaining.is_empty());
+    assert_eq!(vec![PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])])], update.path_attributes);
+    assert_eq!(vec![Prefix::from_str("192.0.2.0/24").unwrap()], update.network_layer_reachability_information);
+
+    // With the four-octet AS number support, the AS_PATH attribute with the same length contains a single AS number
+    let bytes = [
+        0x00, 0x00, 0x00, 0x09, 0x40, 0x02, 0x06, 0x02, 0x01, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x18, 0xC0, 0x00, 0x02
+    ];
+    context.four_octet_asn = true;
+    let (update, _) = UpdateMessage::parse_with(&bytes, &context).unwrap();
+    assert_eq!(vec![PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65536])])], update.path_attributes);
+}
+
+#[test]
+fn pack_update_with_two_octet_asns() {
+    // AS_PATH with a sequence of 2 AS numbers encoded with two bytes
+    let bytes = [0x00, 0x00, 0x00, 0x09, 0x40, 0x02, 0x06, 0x02, 0x02, 0xFD, 0xE9, 0xFD, 0xEA, 0x18, 0xC0, 0x00, 0x02];
+    let context = SessionContext::default();
+    let (mut update, _) = UpdateMessage::parse_with(&bytes, &context).unwrap();
+    assert_eq!(bytes.to_vec(), update.pack_with(&context));
+    assert_eq!(bytes.len(), update.wire_len_with(&context));
+    assert_ne!(bytes.to_vec(), update.pack());
+
+    // AS numbers not fitting into two bytes are sent as AS_TRANS (23456) to peers without four-octet AS number support, the original AS
+    // numbers are sent in the AS4_PATH path attribute
+    update.prepare_for_ebgp(4200000000, IpAddr::from_str("192.0.2.1").unwrap());
+    let bytes = update.pack_with(&context);
+    assert_eq!(bytes.len(), update.wire_len_with(&context));
+    let (decoded, _) = UpdateMessage::parse_with(&bytes, &context).unwrap();
+    let segments = decoded.path_attributes.iter().find_map(|attribute| match attribute {
+        PathAttribute::ASPath(segments) => Some(segments.clone()),
+        _ => None
+    });
+    assert_eq!(Some(vec![ASPathSegment::Sequence(vec![23456, 65001, 65002])]), segments);
+    let as4_path = PathAttribute::Unknown {
+        flags: PathAttributeFlags::OPTIONAL | PathAttributeFlags::TRANSITIVE,
+        kind: 17,
+        data: vec![2, 3, 0xFA, 0x56, 0xEA, 0x00, 0x00, 0x00, 0xFD, 0xE9, 0x00, 0x00, 0xFD, 0xEA]
+    };
+    assert_eq!(Some(&as4_path), decoded.path_attributes.last());
+
+    // A received AS4_PATH path attribute is forwarded instead of adding a second one, peers with four-octet AS numbers don't get one
+    let mut forwarded = update.clone();
+    forwarded.path_attributes.push(as4_path);
+    assert_eq!(bytes, forwarded.pack_with(&context));
+    assert!(UpdateMessage::parse(&update.pack()).unwrap().0.path_attributes.iter().all(|attribute| attribute.kind() != 17));
+}
+
+#[test]
+fn validate_received_update() {
+    let mut update = UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes: vec![
+            PathAttribute::Origin(Origin::IGP),
+            PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001])])
+        ],
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/24").unwrap()]
+    };
+    assert_eq!(Err(UpdateError::MissingWellKnownAttribute(3)), update.validate_received());
+    let notification = NotificationMessage::from(UpdateError::MissingWellKnownAttribute(3));
+    assert_eq!((3, 3, vec![3]), (notification.error_code, notification.error_subcode, notification.data));
+
+    update.path_attributes.push(PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)));
+    assert_eq!(Ok(()), update.validate_received());
+
+    update.path_attributes.push(PathAttribute::Origin(Origin::EGP));
+    assert_eq!(Err(UpdateError::MalformedAttributeList(1)), update.validate_received());
+
+    // Update messages only withdrawing routes don't need the mandatory attributes
+    let withdrawal = UpdateMessage {
+        withdrawn_routes: update.network_layer_reachability_information,
+        path_attributes: vec![],
+        network_layer_reachability_information: vec![]
+    };
+    assert_eq!(Ok(()), withdrawal.validate_received());
+}
+
+#[test]
+fn validate_update_length_fields() {
+    // Empty withdrawn routes, path attributes length of 28 bytes, but only the ORIGIN path attribute (4 bytes) follows
+    let body = [0x00, 0x00, 0x00, 0x1C, 0x40, 0x01, 0x01, 0x00];
+    let error = UpdateError::LengthFieldMismatch { field: "path attributes", declared: 28, available: 4 };
+    assert_eq!(Err(error), UpdateMessage::validate_length_fields(&body));
+    assert_eq!(Err(BgpParseError::MalformedUpdate { offset: 0, error }), UpdateMessage::parse_with(&body, &SessionContext::default()));
+    let mut message = vec![0xFF; 16];
+    message.extend_from_slice(&[0x00, 0x1B, 0x02]);
+    message.extend_from_slice(&body);
+    assert_eq!(Err(error), BGPMessage::validate_update(&message));
+    assert_eq!(Err(BgpParseError::MalformedUpdate { offset: 19, error }), BGPMessage::parse(&message));
+    let notification = NotificationMessage::from(error);
+    assert_eq!((3, 1), (notification.error_code, notification.error_subcode));
+
+    let error = UpdateError::LengthFieldMismatch { field: "withdrawn routes", declared: 256, available: 6 };
+    assert_eq!(Err(error), UpdateMessage::validate_length_fields(&[0x01, 0x00, 0x00, 0x04, 0x40, 0x01, 0x01, 0x00]));
+    assert_eq!(Ok(()), UpdateMessage::validate_length_fields(&[0x00, 0x00, 0x00, 0x04, 0x40, 0x01, 0x01, 0x00]));
+}
+
+#[test]
+fn pack_long_as_path_extended_length() {
+    let asns: Vec<u32> = (65001..65101).collect();
+    let attribute = PathAttribute::ASPath(vec![ASPathSegment::Sequence(asns)]);
+    let bytes = attribute.pack();
+    assert_eq!(PathAttributeFlags::TRANSITIVE | PathAttributeFlags::EXTENDED_LENGTH, PathAttributeFlags::from_bits(bytes[0]).unwrap());
+    assert_eq!(402, u16::from_be_bytes([bytes[2], bytes[3]]));
+    assert_eq!(Ok((attribute, [].as_slice())), PathAttribute::parse(&bytes));
+
+    // Segments with more than 255 AS numbers are split instead of truncating the count
+    let asns: Vec<u32> = (65001..65301).collect();
+    let bytes = PathAttribute::ASPath(vec![ASPathSegment::Sequence(asns.clone())]).pack();
+    let expected = vec![ASPathSegment::Sequence(asns[..255].to_vec()), ASPathSegment::Sequence(asns[255..].to_vec())];
+    assert_eq!(Ok((PathAttribute::ASPath(expected), [].as_slice())), PathAttribute::parse(&bytes));
+
+    // Splitting a set changes the path length, as two sets count as two AS numbers instead of one, so it's rejected by try_pack. When
+    // packed anyway, the set is split like a sequence without dropping any AS number.
+    let attribute = PathAttribute::ASPath(vec![ASPathSegment::Set(asns.clone())]);
+    let error = PackError::TooLong { element: "AS_PATH segment", length: 300, maximum: 255 };
+    assert_eq!(Err(error), attribute.try_pack());
+    let bytes = attribute.pack();
+    assert_eq!(bytes.len(), attribute.wire_len());
+    let expected = PathAttribute::ASPath(vec![ASPathSegment::Set(asns[..255].to_vec()), ASPathSegment::Set(asns[255..].to_vec())]);
+    assert_eq!(Ok((expected, [].as_slice())), PathAttribute::parse(&bytes));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn read_and_write_message_stream() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    let message = BGPMessage::Notification(NotificationMessage { error_code: 6, error_subcode: 2, data: vec![] });
+    write_message(&mut client, &message).await.unwrap();
+    write_message(&mut client, &BGPMessage::KeepAlive).await.unwrap();
+    assert_eq!(message, read_message(&mut server).await.unwrap());
+    assert_eq!(BGPMessage::KeepAlive, read_message(&mut server).await.unwrap());
+
+    // The path attributes length of the update message exceeds the message by 24 bytes
+    let mut bytes = BGPMessage::Update(update_message(vec![PathAttribute::Origin(Origin::IGP)])).pack();
+    bytes[22] += 24;
+    tokio::io::AsyncWriteExt::write_all(&mut client, &bytes).await.unwrap();
+    let error = read_message(&mut server).await.unwrap_err();
+    let update_error = error.get_ref().and_then(|error| error.downcast_ref::<UpdateError>()).unwrap();
+    assert!(matches!(update_error, UpdateError::LengthFieldMismatch { field: "path attributes", .. }));
+
+    drop(client);
+    assert_eq!(std::io::ErrorKind::UnexpectedEof, read_message(&mut server).await.unwrap_err().kind());
+}
+
+#[test]
+fn display_update_summary() {
+    let update = UpdateMessage {
+        withdrawn_routes: vec![Prefix::from_str("198.51.100.0/24").unwrap()],
+        path_attributes: vec![
+            PathAttribute::Origin(Origin::IGP),
+            PathAttribute::ASPath(vec![ASPathSegment::Sequence(vec![65001, 65002])]),
+            PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+            PathAttribute::MpUnreachableNLRI(MultiprotocolUnreachablePathAttribute {
+                address_family: AddressFamily::IPv6,
+                subsequent_address_family: SubsequentAddressFamily::Unicast,
+                network_layer_reachability_information: vec![Prefix::from_str("2001:db8::/32").unwrap()],
+                vpn_network_layer_reachability_information: vec![],
+                evpn_network_layer_reachability_information: vec![]
+            })
+        ],
+        network_layer_reachability_information: vec![
+            Prefix::from_str("192.0.2.0/25").unwrap(),
+            Prefix::from_str("192.0.2.128/25").unwrap(),
+            Prefix::from_str("203.0.113.0/24").unwrap()
+        ]
+    };
+    assert_eq!(
+        "UPDATE: +3 NLRI, -2 withdrawn, attrs=[IGP, AS path 65001 65002, Next hop 192.0.2.1, 1 newly unreachable IPv6 addresses (Unicast)]",
+        update.to_string()
+    );
+}
+
+#[test]
+fn normalize_open_capabilities() {
+    let ipv6 = Capability::MultiprotocolExtensions(MultiprotocolExtensionsCapability {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast
+    });
+    let four_octet_asn = Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 65001 });
+    let open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![
+        OptionalParameter::Capabilities(vec![ipv6.clone(), four_octet_asn.clone()]),
+        OptionalParameter::Unknown { kind: 42, data: vec![] },
+        OptionalParameter::Capabilities(vec![ipv6.clone(), Capability::RouteRefresh])
+    ]).unwrap();
+    assert_eq!(vec![
+        OptionalParameter::Capabilities(vec![ipv6, four_octet_asn, Capability::RouteRefresh]),
+        OptionalParameter::Unknown { kind: 42, data: vec![] }
+    ], open.optional_parameters);
+}
+
+#[test]
+fn reject_update_with_stray_nlri_byte() {
+    let update = UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes: vec![PathAttribute::Origin(Origin::IGP)],
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/24").unwrap()]
+    };
+    let mut bytes = update.pack();
+    assert_eq!(Ok((update, [].as_slice())), UpdateMessage::parse(&bytes));
+
+    // The stray byte declares a /24 prefix without any address bytes following
+    bytes.push(24);
+    assert!(UpdateMessage::parse(&bytes).is_err());
+}
+
+#[test]
+fn render_messages_as_json() {
+    assert_eq!(r#"{"type":"KEEPALIVE"}"#, BGPMessage::KeepAlive.to_json());
+
+    let update = UpdateMessage {
+        withdrawn_routes: vec![Prefix::from_str("198.51.100.0/24").unwrap()],
+        path_attributes: vec![PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1))],
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/24").unwrap()]
+    };
+    assert_eq!(
+        r#"{"withdrawn_routes":["198.51.100.0/24"],"path_attributes":["Next hop 192.0.2.1"],"nlri":["192.0.2.0/24"]}"#,
+        update.to_json()
+    );
+    assert_eq!(
+        r#"{"type":"UPDATE","withdrawn_routes":["198.51.100.0/24"],"path_attributes":["Next hop 192.0.2.1"],"nlri":["192.0.2.0/24"]}"#,
+        BGPMessage::Update(update).to_json()
+    );
+
+    assert_eq!(r#""a\"b\\c\n\u0001""#, crate::protocols::bgp::json_string("a\"b\\c\n\u{1}"));
+}
+
+#[test]
+fn multiprotocol_capability_parameter_round_trip() {
+    let capability = Capability::MultiprotocolExtensions(MultiprotocolExtensionsCapability {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Multicast
+    });
+    let parameter = OptionalParameter::Capabilities(vec![capability]);
+    let bytes = parameter.pack(false);
+    assert_eq!(vec![2, 6, 1, 4, 0, 2, 0, 2], bytes);
+    assert_eq!((&[][..], parameter.clone()), OptionalParameter::unpack(&bytes, false).unwrap());
+    assert_eq!((&[][..], parameter.clone()), OptionalParameter::unpack(&parameter.pack(true), true).unwrap());
+}
+
+#[test]
+fn build_typed_notifications() {
+    let notification = NotificationMessage::cease(CeaseSubcode::AdministrativeShutdown);
+    assert_eq!((6, 2), (notification.error_code, notification.error_subcode));
+    assert!(notification.data.is_empty());
+
+    let notification = NotificationMessage::open_error(OpenErrorSubcode::UnsupportedCapability);
+    assert_eq!((2, 7), (notification.error_code, notification.error_subcode));
+
+    let notification = NotificationMessage::update_error(UpdateErrorSubcode::MalformedASPath, vec![]);
+    assert_eq!((3, 11), (notification.error_code, notification.error_subcode));
+    assert_eq!(NotificationMessage::from(UpdateError::MissingWellKnownAttribute(3)),
+               NotificationMessage::update_error(UpdateErrorSubcode::MissingWellKnownAttribute, vec![3]));
+}
+
+#[test]
+fn try_pack_rejects_overlong_lengths() {
+    let parameters = vec![OptionalParameter::Capabilities(vec![Capability::Unknown { kind: 70, data: vec![0; 300] }])];
+    let open = OpenMessage::new(65000, 90, Ipv4Addr::new(192, 0, 2, 1), parameters).unwrap();
+    assert_eq!(Err(PackError::TooLong { element: "Capability", length: 300, maximum: 255 }), BGPMessage::Open(open).try_pack());
+
+    let attribute = PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL, kind: 99, data: vec![0; 70000] };
+    assert_eq!(Err(PackError::TooLong { element: "Path attribute", length: 70000, maximum: 65535 }), attribute.try_pack());
+
+    let attribute = PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL, kind: 99, data: vec![0; 300] };
+    let bytes = attribute.try_pack().unwrap();
+    assert_eq!((PathAttributeFlags::OPTIONAL | PathAttributeFlags::EXTENDED_LENGTH).bits(), bytes[0]);
+    assert_eq!(304, bytes.len());
+
+    let update = UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes: vec![PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL, kind: 99, data: vec![0; 5000] }],
+        network_layer_reachability_information: vec![]
+    };
+    let error = PackError::TooLong { element: "Message", length: 5027, maximum: MAX_MESSAGE_LENGTH };
+    assert_eq!(Err(error), BGPMessage::Update(update).try_pack());
+    assert_eq!(Ok(BGPMessage::KeepAlive.pack()), BGPMessage::KeepAlive.try_pack());
+}
+
+#[test]
+fn unknown_attribute_keeps_extended_length() {
+    let bytes = [0xF0, 99, 0, 3, 0x01, 0x02, 0x03];
+    let (attribute, _) = PathAttribute::parse(&bytes).unwrap();
+    assert_eq!(bytes.to_vec(), attribute.pack());
+
+    let bytes = [0xE0, 99, 3, 0x01, 0x02, 0x03];
+    let (attribute, _) = PathAttribute::parse(&bytes).unwrap();
+    assert_eq!(bytes.to_vec(), attribute.pack());
+}
+
+#[test]
+fn update_announced_and_withdrawn_prefixes() {
+    let reachable = MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        next_hop_address: Some(MultiprotocolNextHop { address: IpAddr::from_str("2001:db8::1").unwrap(), link_local_address: None }),
+        network_layer_reachability_information: vec![Prefix::from_str("2001:db8:1::/48").unwrap()],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    };
+    let unreachable = MultiprotocolUnreachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        network_layer_reachability_information: vec![Prefix::from_str("2001:db8:2::/48").unwrap()],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    };
+    let update = UpdateMessage {
+        withdrawn_routes: vec![Prefix::from_str("198.51.100.0/24").unwrap()],
+        path_attributes: vec![PathAttribute::MpReachableNLRI(reachable), PathAttribute::MpUnreachableNLRI(unreachable)],
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/24").unwrap()]
+    };
+
+    assert_eq!(vec![
+        (AddressFamily::IPv4, Prefix::from_str("192.0.2.0/24").unwrap()),
+        (AddressFamily::IPv6, Prefix::from_str("2001:db8:1::/48").unwrap())
+    ], update.announced_prefixes());
+    assert_eq!(vec![
+        (AddressFamily::IPv4, Prefix::from_str("198.51.100.0/24").unwrap()),
+        (AddressFamily::IPv6, Prefix::from_str("2001:db8:2::/48").unwrap())
+    ], update.withdrawn_prefixes());
+}
+
+#[test]
+fn marker_validation_strictness() {
+    let standard = BGPMessage::KeepAlive.pack();
+    let mut zeroed = standard.clone();
+    zeroed[..16].fill(0);
+
+    assert_eq!(BGPMessage::KeepAlive, BGPMessage::unpack_with_marker(&standard, true).unwrap().1);
+    assert_eq!(BGPMessage::KeepAlive, BGPMessage::unpack_with_marker(&standard, false).unwrap().1);
+    assert!(BGPMessage::unpack_with_marker(&zeroed, true).is_err());
+    assert_eq!(BGPMessage::KeepAlive, BGPMessage::unpack_with_marker(&zeroed, false).unwrap().1);
+
+    assert!(BGPMessageHeader::peek(&zeroed).is_err());
+    assert_eq!([0; 16], BGPMessageHeader::peek_with(&zeroed, false).unwrap().1.marker);
+    assert_eq!(BGPMessageHeader::STANDARD_MARKER, BGPMessageHeader::peek_with(&standard, false).unwrap().1.marker);
+}
+
+#[test]
+fn parse_extended_message() {
+    let update = UpdateMessage {
+        withdrawn_routes: vec![],
+        path_attributes: vec![PathAttribute::Unknown {
+            flags: PathAttributeFlags::OPTIONAL | PathAttributeFlags::EXTENDED_LENGTH,
+            kind: 99,
+            data: vec![0; 4973]
+        }],
+        network_layer_reachability_information: vec![]
+    };
+    let bytes = BGPMessage::Update(update.clone()).pack();
+    assert_eq!(5000, bytes.len());
+
+    let context = SessionContext { four_octet_asn: true, extended_message: true, ..SessionContext::default() };
+    assert_eq!(Ok((BGPMessage::Update(update), &[][..])), BGPMessage::parse_with(&bytes, &context));
+    assert!(BGPMessage::parse_with(&bytes, &SessionContext::default()).is_err());
+    assert!(BGPMessage::unpack(&bytes).is_err());
+
+    let capability = Capability::ExtendedMessage;
+    assert_eq!(vec![6, 0], capability.pack());
+    assert_eq!(Ok((capability, &[][..])), Capability::parse(&[6, 0]));
+}
+
+#[test]
+fn negotiate_hold_time() {
+    assert_eq!(90, negotiated_hold_time(90, 180));
+    assert_eq!(0, negotiated_hold_time(0, 90));
+    assert_eq!(0, negotiated_hold_time(90, 0));
+
+    let policy = HoldTimePolicy { minimum_hold_time: 30 };
+    assert_eq!(Ok(30), policy.negotiate(90, 30));
+    assert_eq!(Ok(0), policy.negotiate(90, 0));
+    let notification = policy.negotiate(90, 10).unwrap_err();
+    assert_eq!(Some(OpenErrorDetail::UnacceptableHoldTime(10)), notification.open_error_detail());
+    assert!(HoldTimePolicy::default().negotiate(90, 2).is_err());
+    assert_eq!(Ok(3), HoldTimePolicy::default().negotiate(90, 3));
+}
+
+#[test]
+fn long_lived_graceful_restart_capability() {
+    let capability = Capability::LongLivedGracefulRestart(LongLivedGracefulRestartCapability {
+        address_families: vec![
+            LongLivedGracefulRestartFamily {
+                address_family: AddressFamily::IPv4,
+                subsequent_address_family: SubsequentAddressFamily::Unicast,
+                flags: LongLivedGracefulRestartFamily::FORWARDING_STATE,
+                stale_time: 86400
+            },
+            LongLivedGracefulRestartFamily {
+                address_family: AddressFamily::IPv6,
+                subsequent_address_family: SubsequentAddressFamily::Unicast,
+                flags: 0,
+                stale_time: 0xFFFFFF
+            }
+        ]
+    });
+    let bytes = vec![71, 14, 0, 1, 1, 0x80, 0x01, 0x51, 0x80, 0, 2, 1, 0, 0xFF, 0xFF, 0xFF];
+    assert_eq!(bytes, capability.pack());
+    assert_eq!(Ok((capability.clone(), &[][..])), Capability::parse(&bytes));
+
+    let empty = Capability::LongLivedGracefulRestart(LongLivedGracefulRestartCapability { address_families: vec![] });
+    assert_eq!(Ok((empty.clone(), &[][..])), Capability::parse(&[71, 0]));
+    assert!(capability.matches(&empty));
+    assert!(Capability::parse(&[71, 3, 0, 1, 1]).is_err());
+
+    assert_eq!(Community::RFC1997 { global_administrator: 65535, local_administrator: 6 }, Community::LLGR_STALE);
+}
+
+#[test]
+fn wire_len_matches_pack() {
+    let capabilities = vec![
+        Capability::MultiprotocolExtensions(MultiprotocolExtensionsCapability {
+            address_family: AddressFamily::IPv6,
+            subsequent_address_family: SubsequentAddressFamily::Unicast
+        }),
+        Capability::RouteRefresh,
+        Capability::FourOctetASNumberSupport(FourOctetASNumberSupportCapability { as_number: 4200000000 })
+    ];
+    let open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), vec![OptionalParameter::Capabilities(capabilities)]).unwrap();
+    let extended_capabilities: Vec<Capability> = (0..50).map(|index| Capability::raw(200, vec![0, 0, 0, index]).unwrap()).collect();
+    let extended_parameters = vec![OptionalParameter::Capabilities(extended_capabilities)];
+    let extended_open = OpenMessage::new(65001, 90, Ipv4Addr::new(192, 0, 2, 1), extended_parameters).unwrap();
+
+    let unreachable = MultiprotocolUnreachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        network_layer_reachability_information: vec![Prefix::from_str("2001:db8:2::/48").unwrap()],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    };
+    let reachable = MultiprotocolReachablePathAttribute {
+        address_family: AddressFamily::IPv6,
+        subsequent_address_family: SubsequentAddressFamily::Unicast,
+        next_hop_address: Some(MultiprotocolNextHop {
+            address: IpAddr::from_str("2001:db8::1").unwrap(),
+            link_local_address: Some(IpAddr::from_str("fe80::1").unwrap())
+        }),
+        network_layer_reachability_information: vec![Prefix::from_str("2001:db8:1::/48").unwrap(), Prefix::from_str("::/0").unwrap()],
+        vpn_network_layer_reachability_information: vec![],
+        evpn_network_layer_reachability_information: vec![]
+    };
+    let update = UpdateMessage {
+        withdrawn_routes: vec![Prefix::from_str("198.51.100.0/24").unwrap(), Prefix::from_str("10.0.0.0/8").unwrap()],
+        path_attributes: vec![
+            PathAttribute::Origin(Origin::IGP),
+            PathAttribute::ASPath(vec![ASPathSegment::Sequence((0..300).collect()), ASPathSegment::Set(vec![65001])]),
+            PathAttribute::NextHop(Ipv4Addr::new(192, 0, 2, 1)),
+            PathAttribute::LocalPref(100),
+            PathAttribute::Communities(vec![Community::LLGR_STALE]),
+            PathAttribute::ExtendedCommunities(vec![
+                Community::RFC4360Opaque { subkind: Assignment::RouteTarget, flags: CommunityFlags::empty(), value: [0; 6] }
+            ]),
+            PathAttribute::ClusterList(vec![Ipv4Addr::new(192, 0, 2, 2)]),
+            PathAttribute::MpReachableNLRI(reachable),
+            PathAttribute::MpUnreachableNLRI(unreachable),
+            PathAttribute::Unknown { flags: PathAttributeFlags::OPTIONAL | PathAttributeFlags::EXTENDED_LENGTH, kind: 99, data: vec![1, 2] }
+        ],
+        network_layer_reachability_information: vec![Prefix::from_str("192.0.2.0/25").unwrap(), Prefix::from_str("192.0.2.128/32").unwrap()]
+    };
+
+    for message in [
+        BGPMessage::Open(open),
+        BGPMessage::Open(extended_open),
+        BGPMessage::Update(update),
+        BGPMessage::Update(UpdateMessage::end_of_rib(AddressFamily::IPv6, SubsequentAddressFamily::Unicast)),
+        BGPMessage::KeepAlive,
+        BGPMessage::Notification(NotificationMessage::update_error(UpdateErrorSubcode::MalformedASPath, vec![1, 2, 3]))
+    ] {
+        assert_eq!(message.pack().len(), message.wire_len(), "{}", message.type_name());
+    }
+}
+
+#[test]
+#[cfg(feature = "rfc6514")]
+fn pmsi_tunnel_attribute() {
+    // Ingress replication PMSI tunnel of an EVPN inclusive multicast route, whose label field carries the VNI 10001
+    let bytes = [0xC0, 0x16, 9, 0, 6, 0x00, 0x27, 0x11, 192, 0, 2, 1];
+    let (attribute, _) = PathAttribute::parse(&bytes).unwrap();
+    assert_eq!(PathAttribute::PmsiTunnel { flags: 0, tunnel_type: 6, label: 10001, tunnel_id: vec![192, 0, 2, 1] }, attribute);
+    assert_eq!(bytes.to_vec(), attribute.pack());
+    assert_eq!(bytes.len(), attribute.wire_len());
+    assert_eq!("PMSI tunnel c0000201 (Type: 6, Label: 10001)", attribute.to_string());
+}
@@ -0,0 +1,28 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the REST API of the web interface. The API is used to inspect and manage the configuration of the router, like
+//! the configured BGP peers.
+
+pub mod peers;
+
+#[cfg(test)]
+pub mod tests;
+
+use rocket::Route;
+
+/// This function returns all routes of the REST API, so they can be mounted into the Rocket instance.
+pub fn routes() -> Vec<Route> {
+    rocket::routes![peers::list_peers, peers::create_peer, peers::delete_peer]
+}
@@ -0,0 +1,81 @@
+// Copyright 2025 Cedric Hammes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde::Deserialize;
+use crate::database::peer;
+
+/// This struct is the body of the request creating a new BGP peer. The values are validated before the peer is inserted into the database.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CreatePeerRequest {
+    pub name: String,
+    pub remote_address: String,
+    pub remote_asn: i64,
+    pub local_asn: i64,
+    pub hold_time: u16,
+    pub enabled: bool
+}
+
+fn internal_error(error: sea_orm::DbErr) -> Custom<String> {
+    Custom(Status::InternalServerError, error.to_string())
+}
+
+#[rocket::get("/peers")]
+pub async fn list_peers(database: &State<DatabaseConnection>) -> Result<Json<Vec<peer::Model>>, Custom<String>> {
+    Ok(Json(peer::Entity::find().all(database.inner()).await.map_err(internal_error)?))
+}
+
+#[rocket::post("/peers", data = "<request>")]
+pub async fn create_peer(database: &State<DatabaseConnection>, request: Json<CreatePeerRequest>) -> Result<Json<peer::Model>, Custom<String>> {
+    let request = request.into_inner();
+    if let Err(error) = IpAddr::from_str(&request.remote_address) {
+        return Err(Custom(Status::BadRequest, format!("Invalid remote address '{}' => {}", request.remote_address, error)));
+    }
+
+    for asn in [request.remote_asn, request.local_asn] {
+        if u32::try_from(asn).is_err() {
+            return Err(Custom(Status::BadRequest, format!("AS number {} doesn't fit into 4 bytes", asn)));
+        }
+    }
+
+    // The hold time must be zero or at least three seconds (RFC 4271, Section 4.2)
+    if matches!(request.hold_time, 1 | 2) {
+        return Err(Custom(Status::BadRequest, format!("Unacceptable hold time of {} seconds", request.hold_time)));
+    }
+
+    // TODO: Create a session for the peer when the BGP server is implemented
+    let peer = peer::ActiveModel {
+        name: Set(request.name),
+        remote_address: Set(request.remote_address),
+        remote_asn: Set(request.remote_asn),
+        local_asn: Set(request.local_asn),
+        hold_time: Set(request.hold_time as i32),
+        enabled: Set(request.enabled),
+        ..Default::default()
+    };
+    Ok(Json(peer.insert(database.inner()).await.map_err(internal_error)?))
+}
+
+#[rocket::delete("/peers/<id>")]
+pub async fn delete_peer(database: &State<DatabaseConnection>, id: i32) -> Result<Status, Custom<String>> {
+    // TODO: Close the session of the peer when the BGP server is implemented
+    let result = peer::Entity::delete_by_id(id).exec(database.inner()).await.map_err(internal_error)?;
+    Ok(if result.rows_affected == 0 { Status::NotFound } else { Status::NoContent })
+}
@@ -0,0 +1,54 @@
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use sea_orm::Database;
+use crate::database::{migrate, peer};
+
+async fn create_client() -> Client {
+    let database = Database::connect("sqlite::memory:").await.unwrap();
+    migrate(&database).await.unwrap();
+    Client::tracked(rocket::build().manage(database).mount("/", crate::web::routes())).await.unwrap()
+}
+
+#[rocket::async_test]
+async fn create_list_and_delete_peer() {
+    let client = create_client().await;
+    let response = client.post("/peers")
+        .header(ContentType::JSON)
+        .body(r#"{"name":"Upstream","remote_address":"2001:db8::1","remote_asn":4200000000,"local_asn":65001,"hold_time":90,"enabled":true}"#)
+        .dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let created: peer::Model = response.into_json().await.unwrap();
+    assert_eq!("2001:db8::1", created.remote_address);
+
+    let peers: Vec<peer::Model> = client.get("/peers").dispatch().await.into_json().await.unwrap();
+    assert_eq!(vec![created.clone()], peers);
+
+    assert_eq!(Status::NoContent, client.delete(format!("/peers/{}", created.id)).dispatch().await.status());
+    assert_eq!(Status::NotFound, client.delete(format!("/peers/{}", created.id)).dispatch().await.status());
+    let peers: Vec<peer::Model> = client.get("/peers").dispatch().await.into_json().await.unwrap();
+    assert!(peers.is_empty());
+}
+
+#[rocket::async_test]
+async fn reject_invalid_peer() {
+    let client = create_client().await;
+    let response = client.post("/peers")
+        .header(ContentType::JSON)
+        .body(r#"{"name":"Upstream","remote_address":"192.0.2","remote_asn":65002,"local_asn":65001,"hold_time":90,"enabled":true}"#)
+        .dispatch().await;
+    assert_eq!(Status::BadRequest, response.status());
+
+    let response = client.post("/peers")
+        .header(ContentType::JSON)
+        .body(r#"{"name":"Upstream","remote_address":"192.0.2.1","remote_asn":4294967296,"local_asn":65001,"hold_time":90,"enabled":true}"#)
+        .dispatch().await;
+    assert_eq!(Status::BadRequest, response.status());
+
+    for body in [
+        r#"{"name":"Upstream","remote_address":"192.0.2.1","remote_asn":65002,"local_asn":65001,"hold_time":1,"enabled":true}"#,
+        r#"{"name":"Upstream","remote_address":"192.0.2.1","remote_asn":65002,"local_asn":65001,"hold_time":2,"enabled":true}"#
+    ] {
+        let response = client.post("/peers").header(ContentType::JSON).body(body).dispatch().await;
+        assert_eq!(Status::BadRequest, response.status());
+    }
+}